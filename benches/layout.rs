@@ -0,0 +1,249 @@
+// Compares the storage of `TwoWayMap`, where the pairs live in the slots of a slab and both
+// indexes hold slot ids, with the previous layout that allocated the left and the right
+// value separately and kept them in two `BTreeMap<Arc<_>, Arc<_>>`. `reinsert` fills the
+// map again after every pair was removed, which the slab does in the slots it freed. The
+// pairs are `u64 <-> u64`, so the values allocate nothing themselves.
+//
+// `footprint` is how much the resident memory of a fresh process grows per pair while it
+// fills a map, read from `/proc/self/status`. The crate forbids `unsafe`, so a counting
+// global allocator isn't an option, and the number is only printed on Linux.
+//
+// Run with `cargo bench --bench layout`.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::hint::black_box;
+use std::process::Command;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use two_way_map::TwoWayMap;
+
+// The layout `TwoWayMap` used before: one allocation per value
+struct TwoAllocationMap<L, R> {
+    left_to_right: BTreeMap<Arc<L>, Arc<R>>,
+    right_to_left: BTreeMap<Arc<R>, Arc<L>>,
+}
+
+impl<L: Ord, R: Ord> TwoAllocationMap<L, R> {
+    fn new() -> Self {
+        Self {
+            left_to_right: BTreeMap::new(),
+            right_to_left: BTreeMap::new(),
+        }
+    }
+
+    fn insert(&mut self, left: L, right: R) {
+        self.remove_by_left(&left);
+        self.remove_by_right(&right);
+
+        let left = Arc::new(left);
+        let right = Arc::new(right);
+        self.left_to_right.insert(left.clone(), right.clone());
+        self.right_to_left.insert(right, left);
+    }
+
+    fn get_by_left(&self, left: &L) -> Option<&R> {
+        self.left_to_right.get(left).map(|right| right.as_ref())
+    }
+
+    fn get_by_right(&self, right: &R) -> Option<&L> {
+        self.right_to_left.get(right).map(|left| left.as_ref())
+    }
+
+    fn remove_by_left(&mut self, left: &L) -> Option<(L, R)> {
+        let (left, right) = self.left_to_right.remove_entry(left)?;
+        self.right_to_left.remove(&right);
+        Some((
+            Arc::try_unwrap(left).ok().unwrap(),
+            Arc::try_unwrap(right).ok().unwrap(),
+        ))
+    }
+
+    fn remove_by_right(&mut self, right: &R) -> Option<(R, L)> {
+        let (right, left) = self.right_to_left.remove_entry(right)?;
+        self.left_to_right.remove(&left);
+        Some((
+            Arc::try_unwrap(right).ok().unwrap(),
+            Arc::try_unwrap(left).ok().unwrap(),
+        ))
+    }
+
+    fn pairs(&self) -> impl Iterator<Item = (&L, &R)> {
+        self.left_to_right
+            .iter()
+            .map(|(left, right)| (left.as_ref(), right.as_ref()))
+    }
+}
+
+// Keys in a scrambled but reproducible order, so the trees don't only grow on one edge
+fn keys(n: u64) -> Vec<u64> {
+    (0..n)
+        .map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15) % (n * 4))
+        .collect()
+}
+
+// A bijection, so the right values are distinct too, in another order than the left ones
+fn right_of(key: u64) -> u64 {
+    key.rotate_left(32)
+}
+
+fn report(name: &str, layout: &str, ops: usize, elapsed: Duration) {
+    let per_op = elapsed.as_nanos() as f64 / ops as f64;
+    println!("{name:<24} {layout:<16} {per_op:>10.1} ns/op");
+}
+
+fn bench_new_layout(n: u64) {
+    let keys = keys(n);
+    let ops = keys.len();
+
+    let start = Instant::now();
+    let mut map = TwoWayMap::new();
+    for &key in &keys {
+        map.insert(key, right_of(key));
+    }
+    report("insert", "slab", ops, start.elapsed());
+
+    let start = Instant::now();
+    for &key in &keys {
+        black_box(map.get_by_left(&key));
+    }
+    report("get_by_left", "slab", ops, start.elapsed());
+
+    let start = Instant::now();
+    for &key in &keys {
+        black_box(map.get_by_right(&right_of(key)));
+    }
+    report("get_by_right", "slab", ops, start.elapsed());
+
+    let start = Instant::now();
+    black_box(map.pairs().count());
+    report("pairs", "slab", ops, start.elapsed());
+
+    let start = Instant::now();
+    for &key in &keys {
+        black_box(map.remove_by_left(&key));
+    }
+    report("remove_by_left", "slab", ops, start.elapsed());
+
+    let start = Instant::now();
+    for &key in &keys {
+        map.insert(key, right_of(key));
+    }
+    report("reinsert", "slab", ops, start.elapsed());
+}
+
+fn bench_old_layout(n: u64) {
+    let keys = keys(n);
+    let ops = keys.len();
+
+    let start = Instant::now();
+    let mut map = TwoAllocationMap::new();
+    for &key in &keys {
+        map.insert(key, right_of(key));
+    }
+    report("insert", "two allocs", ops, start.elapsed());
+
+    let start = Instant::now();
+    for &key in &keys {
+        black_box(map.get_by_left(&key));
+    }
+    report("get_by_left", "two allocs", ops, start.elapsed());
+
+    let start = Instant::now();
+    for &key in &keys {
+        black_box(map.get_by_right(&right_of(key)));
+    }
+    report("get_by_right", "two allocs", ops, start.elapsed());
+
+    let start = Instant::now();
+    black_box(map.pairs().count());
+    report("pairs", "two allocs", ops, start.elapsed());
+
+    let start = Instant::now();
+    for &key in &keys {
+        black_box(map.remove_by_left(&key));
+    }
+    report("remove_by_left", "two allocs", ops, start.elapsed());
+
+    let start = Instant::now();
+    for &key in &keys {
+        map.insert(key, right_of(key));
+    }
+    report("reinsert", "two allocs", ops, start.elapsed());
+}
+
+fn resident_bytes() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+// Runs in a child process, so that memory freed by an earlier map can't be reused
+fn measure_footprint(layout: &str, n: u64) {
+    let keys = keys(n);
+    let Some(before) = resident_bytes() else {
+        return;
+    };
+    let after = if layout == "slab" {
+        let mut map = TwoWayMap::new();
+        for &key in &keys {
+            map.insert(key, right_of(key));
+        }
+        let after = resident_bytes();
+        black_box(&map);
+        after
+    } else {
+        let mut map = TwoAllocationMap::new();
+        for &key in &keys {
+            map.insert(key, right_of(key));
+        }
+        let after = resident_bytes();
+        black_box(&map);
+        after
+    };
+    if let Some(after) = after {
+        println!("{:.1}", after.saturating_sub(before) as f64 / n as f64);
+    }
+}
+
+fn report_footprint(layout: &str, n: u64) {
+    let Ok(exe) = env::current_exe() else {
+        return;
+    };
+    let output = Command::new(exe)
+        .args(["footprint", layout, &n.to_string()])
+        .output();
+    let Some(per_pair) = output
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .and_then(|stdout| stdout.trim().parse::<f64>().ok())
+    else {
+        return;
+    };
+    println!("{:<24} {layout:<16} {per_pair:>10.1} B/pair", "footprint");
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let [command, layout, n] = args.as_slice()
+        && command == "footprint"
+    {
+        measure_footprint(layout, n.parse().unwrap());
+        return;
+    }
+
+    for n in [1_000, 100_000, 1_000_000] {
+        println!("--- {n} pairs ---");
+        bench_old_layout(n);
+        bench_new_layout(n);
+        // A thousand pairs fit in a few pages, too few to tell the layouts apart
+        if n >= 100_000 {
+            report_footprint("two allocs", n);
+            report_footprint("slab", n);
+        }
+    }
+}
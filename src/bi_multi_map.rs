@@ -0,0 +1,442 @@
+use std::borrow::Borrow;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Debug};
+use std::mem;
+use std::ops::{Bound, RangeBounds};
+
+use crate::iter::{
+    GroupRange, LeftsByRight, MultiLeftRange, MultiPairs, MultiPairsByRight, MultiRightRange,
+    RightsByLeft,
+};
+use crate::mem::{ByLeft, ByRight, Field, Pair, Wrapper, wrap_range, wrap_ref};
+use crate::unwind::Undo;
+
+// A many-to-many relation: a set of pairs in which a left value can be paired with any
+// number of right values, and the other way around. As in `TwoWayMap`, both values of a
+// pair live in one allocation that both indexes hold a handle to. Each index maps a value to
+// the group of pairs it's in, ordered by their other value, and the key of a group is one
+// more handle to one of its pairs, so that no value has to be cloned.
+//
+// As for `TwoWayMap`, a panicking `Ord` implementation leaves the map as it was before
+// `insert`, `remove_pair` or the `remove_all_by_*` methods were called.
+pub struct BiMultiMap<L, R> {
+    left_to_right: Index<L, R, ByLeft, ByRight>,
+    right_to_left: Index<L, R, ByRight, ByLeft>,
+    len: usize,
+}
+
+// Groups of handles keyed by `G`, each under a handle keyed by `F` to one of its pairs.
+// Groups are never empty.
+pub(crate) type Index<L, R, F, G> = BTreeMap<Pair<L, R, F>, BTreeSet<Pair<L, R, G>>>;
+
+impl<L, R> BiMultiMap<L, R> {
+    pub fn new() -> Self {
+        Self {
+            left_to_right: BTreeMap::new(),
+            right_to_left: BTreeMap::new(),
+            len: 0,
+        }
+    }
+
+    // The number of pairs
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // The number of distinct left values
+    pub fn left_len(&self) -> usize {
+        self.left_to_right.len()
+    }
+
+    // The number of distinct right values
+    pub fn right_len(&self) -> usize {
+        self.right_to_left.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.left_to_right.clear();
+        self.right_to_left.clear();
+        self.len = 0;
+    }
+
+    pub fn pairs(&self) -> MultiPairs<'_, L, R> {
+        MultiPairs::new(self.left_to_right.values().flatten())
+    }
+
+    pub fn pairs_by_right(&self) -> MultiPairsByRight<'_, L, R> {
+        MultiPairsByRight::new(self.right_to_left.values().flatten())
+    }
+}
+
+impl<L: Ord, R: Ord> BiMultiMap<L, R> {
+    // Returns `false`, dropping both values, if the map holds the pair already
+    pub fn insert(&mut self, left: L, right: R) -> bool {
+        if self.contains_pair(&left, &right) {
+            return false;
+        }
+        self.attach(Pair::new(left, right));
+        true
+    }
+
+    fn attach(&mut self, pair: Pair<L, R, ByRight>) {
+        attach_pair(
+            &mut self.right_to_left,
+            add,
+            |index, pair| drop(take(index, pair.right(), pair.left())),
+            &mut self.left_to_right,
+            pair,
+        );
+        self.len += 1;
+    }
+
+    pub fn contains_pair<Q1, Q2>(&self, left: &Q1, right: &Q2) -> bool
+    where
+        L: Borrow<Q1>,
+        R: Borrow<Q2>,
+        Q1: Ord + ?Sized,
+        Q2: Ord + ?Sized,
+    {
+        self.left_to_right
+            .get(wrap_ref(left))
+            .is_some_and(|group| group.contains(wrap_ref(right)))
+    }
+
+    pub fn contains_left<Q>(&self, left: &Q) -> bool
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.left_to_right.contains_key(wrap_ref(left))
+    }
+
+    pub fn contains_right<Q>(&self, right: &Q) -> bool
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.right_to_left.contains_key(wrap_ref(right))
+    }
+
+    // The right values paired with `left`, in order
+    pub fn get_by_left<Q>(&self, left: &Q) -> RightsByLeft<'_, L, R>
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let group = self.left_to_right.get(wrap_ref(left));
+        RightsByLeft::new(group.map(BTreeSet::iter).unwrap_or_default())
+    }
+
+    // The left values paired with `right`, in order
+    pub fn get_by_right<Q>(&self, right: &Q) -> LeftsByRight<'_, L, R>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let group = self.right_to_left.get(wrap_ref(right));
+        LeftsByRight::new(group.map(BTreeSet::iter).unwrap_or_default())
+    }
+
+    pub fn count_by_left<Q>(&self, left: &Q) -> usize
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.left_to_right
+            .get(wrap_ref(left))
+            .map_or(0, BTreeSet::len)
+    }
+
+    pub fn count_by_right<Q>(&self, right: &Q) -> usize
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.right_to_left
+            .get(wrap_ref(right))
+            .map_or(0, BTreeSet::len)
+    }
+
+    pub fn remove_pair<Q1, Q2>(&mut self, left: &Q1, right: &Q2) -> Option<(L, R)>
+    where
+        L: Borrow<Q1>,
+        R: Borrow<Q2>,
+        Q1: Ord + ?Sized,
+        Q2: Ord + ?Sized,
+    {
+        let pair = take(&mut self.left_to_right, left, right)?;
+        let pair = detach_pair(&mut self.left_to_right, pair, add, &mut self.right_to_left);
+        self.len -= 1;
+        Some(pair)
+    }
+
+    // Removes the pairs of `left`, returning them in the order of their right values
+    pub fn remove_all_by_left<Q>(&mut self, left: &Q) -> Vec<(L, R)>
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let Some(group) = take_group(&mut self.left_to_right, &mut self.right_to_left, left) else {
+            return Vec::new();
+        };
+        self.len -= group.len();
+        group.into_iter().map(Pair::into_values).collect()
+    }
+
+    // Removes the pairs of `right`, returning them in the order of their left values
+    pub fn remove_all_by_right<Q>(&mut self, right: &Q) -> Vec<(R, L)>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let Some(group) = take_group(&mut self.right_to_left, &mut self.left_to_right, right)
+        else {
+            return Vec::new();
+        };
+        self.len -= group.len();
+        group
+            .into_iter()
+            .map(|pair| {
+                let (left, right) = Pair::into_values(pair);
+                (right, left)
+            })
+            .collect()
+    }
+
+    // The pairs whose left value is in `range`, in the same order as `pairs`
+    pub fn left_range<Q>(&self, range: impl RangeBounds<Q>) -> MultiLeftRange<'_, L, R>
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        MultiLeftRange::new(groups_in(&self.left_to_right, &range))
+    }
+
+    // The pairs whose right value is in `range`, in the same order as `pairs_by_right`
+    pub fn right_range<Q>(&self, range: impl RangeBounds<Q>) -> MultiRightRange<'_, L, R>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        MultiRightRange::new(groups_in(&self.right_to_left, &range))
+    }
+}
+
+// Puts a handle into the group of its `F` value, which is made if there's none. If a
+// comparison panics, the index is left as it was.
+pub(crate) fn add<L, R, F, G>(index: &mut Index<L, R, F, G>, pair: Pair<L, R, G>)
+where
+    F: Field<L, R>,
+    G: Field<L, R>,
+    F::Key: Ord,
+    G::Key: Ord,
+{
+    match index.entry(pair.share()) {
+        Entry::Occupied(mut group) => {
+            group.get_mut().insert(pair);
+        }
+        Entry::Vacant(group) => {
+            group.insert(BTreeSet::from([pair]));
+        }
+    }
+}
+
+// Puts the handles to a new pair into `first` with `add_first`, then into `second`. If
+// adding it to `second` panics, `remove_first` takes it out of `first` again.
+pub(crate) fn attach_pair<S, L, R, F, G, H>(
+    first: &mut S,
+    add_first: fn(&mut S, Pair<L, R, F>),
+    remove_first: fn(&mut S, Pair<L, R, F>),
+    second: &mut Index<L, R, G, H>,
+    pair: Pair<L, R, H>,
+) where
+    G: Field<L, R>,
+    H: Field<L, R>,
+    G::Key: Ord,
+    H::Key: Ord,
+{
+    add_first(first, pair.share());
+    let added = Undo::new(first, pair.share(), remove_first);
+    add(second, pair);
+    drop(added.done());
+}
+
+// Takes the other handle to a pair just taken out of `first` out of `second`, and moves the
+// pair out. If that panics, `put_back` puts the pair back into `first`.
+pub(crate) fn detach_pair<S, L, R, F, G, H>(
+    first: &mut S,
+    pair: Pair<L, R, F>,
+    put_back: fn(&mut S, Pair<L, R, F>),
+    second: &mut Index<L, R, G, H>,
+) -> (L, R)
+where
+    G: Field<L, R>,
+    H: Field<L, R>,
+    G::Key: Ord,
+    H::Key: Ord,
+{
+    let pair = Undo::new(first, pair, put_back);
+    drop(take(second, pair.key_by::<G>(), pair.key_by::<H>()));
+    // The other handles are gone, so the pair can be moved out
+    Pair::into_values(pair.done())
+}
+
+// Takes the handle to the pair of `key` and `other` out of the group of `key`. A group left
+// empty is removed, and one whose key is a handle to that pair is moved under a handle to
+// another of its pairs. If a comparison panics, the index is left as it was.
+pub(crate) fn take<L, R, F, G, Q1, Q2>(
+    index: &mut Index<L, R, F, G>,
+    key: &Q1,
+    other: &Q2,
+) -> Option<Pair<L, R, G>>
+where
+    F: Field<L, R>,
+    G: Field<L, R>,
+    F::Key: Borrow<Q1> + Ord,
+    G::Key: Borrow<Q2> + Ord,
+    Q1: Ord + ?Sized,
+    Q2: Ord + ?Sized,
+{
+    let bounds = (
+        Bound::Included(wrap_ref(key)),
+        Bound::Included(wrap_ref(key)),
+    );
+    let (first, group) = index.range_mut::<Wrapper<Q1>, _>(bounds).next()?;
+    let pair = group.take(wrap_ref(other))?;
+    let emptied = group.is_empty();
+    if !emptied && !first.same_pair(&pair) {
+        return Some(pair);
+    }
+
+    let mut pair = Undo::new(index, pair, add);
+    if emptied {
+        pair.target().remove(wrap_ref(key));
+        return Some(pair.done());
+    }
+
+    // `BTreeMap` can't change a key in place, so the group is taken out, and `entry` finds
+    // where it goes back before anything has to be moved
+    let (_, rest) = pair.target().remove_entry(wrap_ref(key)).unwrap();
+    let pair = pair.done();
+    let mut moved = Undo::new(index, (pair, rest), |index, (pair, mut rest)| {
+        rest.insert(pair.share());
+        index.insert(pair.share(), rest);
+    });
+    let (index, (_, rest)) = moved.split();
+    let key = rest.first().unwrap().share();
+    mem::swap(index.entry(key).or_default(), rest);
+    let (pair, _) = moved.done();
+    Some(pair)
+}
+
+// Takes the group of `key` out of `index`, and the handles to its pairs out of `other`. If a
+// comparison panics, both indexes are left as they were.
+fn take_group<L, R, F, G, Q>(
+    index: &mut Index<L, R, F, G>,
+    other: &mut Index<L, R, G, F>,
+    key: &Q,
+) -> Option<BTreeSet<Pair<L, R, G>>>
+where
+    F: Field<L, R>,
+    G: Field<L, R>,
+    F::Key: Borrow<Q> + Ord,
+    G::Key: Ord,
+    Q: Ord + ?Sized,
+{
+    let (_, group) = index.remove_entry(wrap_ref(key))?;
+    let mut indexes = (index, other);
+    let mut guard = Undo::new(
+        &mut indexes,
+        (group, Vec::new()),
+        |(index, other), (group, taken)| {
+            for pair in taken {
+                add(other, pair);
+            }
+            let key = group.first().unwrap().share();
+            index.insert(key, group);
+        },
+    );
+    let ((_, other), (group, taken)) = guard.split();
+    for pair in group.iter() {
+        let handle =
+            take::<_, _, _, _, G::Key, F::Key>(other, pair.key_by::<G>(), pair.key_by::<F>());
+        taken.push(handle.unwrap());
+    }
+    let (group, _) = guard.done();
+    Some(group)
+}
+
+pub(crate) fn groups_in<'a, K, T, Q>(
+    index: &'a BTreeMap<K, BTreeSet<T>>,
+    range: &impl RangeBounds<Q>,
+) -> GroupRange<'a, K, T>
+where
+    K: Borrow<Wrapper<Q>> + Ord,
+    Q: Ord + ?Sized,
+{
+    let group: fn((&'a K, &'a BTreeSet<T>)) -> &'a BTreeSet<T> = |(_, group)| group;
+    index
+        .range::<Wrapper<Q>, _>(wrap_range(range))
+        .flat_map(group)
+}
+
+impl<L, R> Default for BiMultiMap<L, R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L: Ord + Clone, R: Ord + Clone> Clone for BiMultiMap<L, R> {
+    fn clone(&self) -> Self {
+        let mut other = BiMultiMap::new();
+        for (left, right) in self.pairs() {
+            other.attach(Pair::new(left.clone(), right.clone()));
+        }
+        other
+    }
+}
+
+impl<L: Debug, R: Debug> Debug for BiMultiMap<L, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.pairs()).finish()
+    }
+}
+
+impl<L: PartialEq, R: PartialEq> PartialEq for BiMultiMap<L, R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.pairs().eq(other.pairs())
+    }
+}
+
+impl<L: Eq, R: Eq> Eq for BiMultiMap<L, R> {}
+
+impl<L: Ord, R: Ord> Extend<(L, R)> for BiMultiMap<L, R> {
+    fn extend<T: IntoIterator<Item = (L, R)>>(&mut self, iter: T) {
+        for (left, right) in iter {
+            self.insert(left, right);
+        }
+    }
+}
+
+impl<L: Ord, R: Ord> FromIterator<(L, R)> for BiMultiMap<L, R> {
+    fn from_iter<T: IntoIterator<Item = (L, R)>>(iter: T) -> Self {
+        let mut map = BiMultiMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<'a, L, R> IntoIterator for &'a BiMultiMap<L, R> {
+    type Item = (&'a L, &'a R);
+
+    type IntoIter = MultiPairs<'a, L, R>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pairs()
+    }
+}
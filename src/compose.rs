@@ -0,0 +1,245 @@
+use std::fmt::{self, Debug};
+use std::iter::{FusedIterator, Peekable};
+
+use crate::iter::{Pairs, PairsByRight};
+use crate::side::{Ordered, Side, SideKey, SideLookup};
+use crate::slab::Slab;
+use crate::two_way_map::TwoWayMap;
+
+// Chains a map from `A` to `B` with one from `B` to `C`. Composing two one-to-one mappings
+// gives another one, so `compose` builds a `TwoWayMap<A, C>`, while a `ComposedView` goes
+// through the middle value on every lookup instead. `join` walks the right index of the
+// first map next to the left index of the second one, the way two sorted lists are merged,
+// and also yields the pairs whose middle value only one of the maps has.
+
+// What `join` found for one middle value
+#[derive(Debug, PartialEq, Eq)]
+pub enum Joined<'a, A, B, C> {
+    Both(&'a A, &'a B, &'a C),
+    // A pair of the first map whose right value isn't a left value of the second one
+    First(&'a A, &'a B),
+    // A pair of the second map whose left value isn't a right value of the first one
+    Second(&'a B, &'a C),
+}
+
+pub struct Join<'a, A, B, C> {
+    first: Peekable<PairsByRight<'a, A, B>>,
+    second: Peekable<Pairs<'a, B, C>>,
+}
+
+impl<A, B: Ord, LS: Side> TwoWayMap<A, B, LS, Ordered> {
+    // Goes through the middle values of both maps in order
+    pub fn join<'a, C, RS: Side>(
+        &'a self,
+        other: &'a TwoWayMap<B, C, Ordered, RS>,
+    ) -> Join<'a, A, B, C> {
+        Join {
+            first: self.pairs_by_right().peekable(),
+            second: other.pairs().peekable(),
+        }
+    }
+
+    // Maps each left value of this map to the right value of `other` its right value is
+    // mapped to. Pairs whose middle value is missing from the other map are left out.
+    pub fn compose<C, RS: Side>(&self, other: &TwoWayMap<B, C, Ordered, RS>) -> TwoWayMap<A, C>
+    where
+        A: Ord + Clone,
+        C: Ord + Clone,
+    {
+        // Both maps are one-to-one, so no two composed pairs share a value
+        let mut map = TwoWayMap::new();
+        for joined in self.join(other) {
+            if let Joined::Both(a, _, c) = joined {
+                map.attach(a.clone(), c.clone());
+            }
+        }
+        map.debug_check();
+        map
+    }
+}
+
+impl<'a, A, B: Ord, C> Iterator for Join<'a, A, B, C> {
+    type Item = Joined<'a, A, B, C>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = |(a, b): (&'a A, &'a B)| Joined::First(a, b);
+        let second = |(b, c): (&'a B, &'a C)| Joined::Second(b, c);
+        match (self.first.peek(), self.second.peek()) {
+            (None, None) => None,
+            (Some(_), None) => self.first.next().map(first),
+            (None, Some(_)) => self.second.next().map(second),
+            (Some((_, a)), Some((b, _))) => match a.cmp(b) {
+                std::cmp::Ordering::Less => self.first.next().map(first),
+                std::cmp::Ordering::Greater => self.second.next().map(second),
+                std::cmp::Ordering::Equal => {
+                    let (a, b) = self.first.next()?;
+                    let (_, c) = self.second.next()?;
+                    Some(Joined::Both(a, b, c))
+                }
+            },
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (first, _) = self.first.size_hint();
+        let (second, _) = self.second.size_hint();
+        (first.max(second), first.checked_add(second))
+    }
+}
+
+impl<A, B: Ord, C> FusedIterator for Join<'_, A, B, C> {}
+
+impl<A, B, C> Clone for Join<'_, A, B, C> {
+    fn clone(&self) -> Self {
+        Join {
+            first: self.first.clone(),
+            second: self.second.clone(),
+        }
+    }
+}
+
+impl<A: Debug, B: Debug, C: Debug> Debug for Join<'_, A, B, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Join")
+            .field("first", &self.first)
+            .field("second", &self.second)
+            .finish()
+    }
+}
+
+// `LS1` and `RS1` are the sides of the first map, `LS2` and `RS2` those of the second one
+pub struct ComposedView<'a, A, B, C, LS1, RS1, LS2, RS2>
+where
+    LS1: Side,
+    RS1: Side,
+    LS2: Side,
+    RS2: Side,
+{
+    first: &'a TwoWayMap<A, B, LS1, RS1>,
+    second: &'a TwoWayMap<B, C, LS2, RS2>,
+}
+
+// The pairs of a `ComposedView` in the order of the left index of the first map
+pub struct ComposedPairs<'a, A, B, C, LS1: Side, LS2: Side> {
+    first: Pairs<'a, A, B, LS1>,
+    second: &'a LS2::Index,
+    slab: &'a Slab<B, C>,
+}
+
+impl<A, B, LS1: Side, RS1: Side> TwoWayMap<A, B, LS1, RS1> {
+    pub fn compose_view<'a, C, LS2: Side, RS2: Side>(
+        &'a self,
+        other: &'a TwoWayMap<B, C, LS2, RS2>,
+    ) -> ComposedView<'a, A, B, C, LS1, RS1, LS2, RS2> {
+        ComposedView {
+            first: self,
+            second: other,
+        }
+    }
+}
+
+impl<'a, A, B, C, LS1, RS1, LS2, RS2> ComposedView<'a, A, B, C, LS1, RS1, LS2, RS2>
+where
+    LS1: Side,
+    RS1: SideKey<B>,
+    LS2: SideKey<B>,
+    RS2: Side,
+{
+    pub fn get_by_left<Q>(&self, left: &Q) -> Option<&'a C>
+    where
+        LS1: SideLookup<A, Q>,
+        Q: ?Sized,
+    {
+        let (first, second) = (self.first, self.second);
+        let id = LS1::find(&first.left_to_right, first.slab.by_left(), left)?;
+        let middle = first.slab.right(id);
+        let id = LS2::find(&second.left_to_right, second.slab.by_left(), middle)?;
+        Some(second.slab.right(id))
+    }
+
+    pub fn get_by_right<Q>(&self, right: &Q) -> Option<&'a A>
+    where
+        RS2: SideLookup<C, Q>,
+        Q: ?Sized,
+    {
+        let (first, second) = (self.first, self.second);
+        let id = RS2::find(&second.right_to_left, second.slab.by_right(), right)?;
+        let middle = second.slab.left(id);
+        let id = RS1::find(&first.right_to_left, first.slab.by_right(), middle)?;
+        Some(first.slab.left(id))
+    }
+
+    pub fn contains_left<Q>(&self, left: &Q) -> bool
+    where
+        LS1: SideLookup<A, Q>,
+        Q: ?Sized,
+    {
+        self.get_by_left(left).is_some()
+    }
+
+    pub fn contains_right<Q>(&self, right: &Q) -> bool
+    where
+        RS2: SideLookup<C, Q>,
+        Q: ?Sized,
+    {
+        self.get_by_right(right).is_some()
+    }
+
+    pub fn pairs(&self) -> ComposedPairs<'a, A, B, C, LS1, LS2> {
+        ComposedPairs {
+            first: self.first.pairs(),
+            second: &self.second.left_to_right,
+            slab: &self.second.slab,
+        }
+    }
+}
+
+impl<'a, A, B: Ord, C, LS1: Side, RS2: Side> ComposedView<'a, A, B, C, LS1, Ordered, Ordered, RS2> {
+    pub fn join(&self) -> Join<'a, A, B, C> {
+        self.first.join(self.second)
+    }
+}
+
+impl<A, B, C, LS1, RS1, LS2, RS2> Clone for ComposedView<'_, A, B, C, LS1, RS1, LS2, RS2>
+where
+    LS1: Side,
+    RS1: Side,
+    LS2: Side,
+    RS2: Side,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<A, B, C, LS1, RS1, LS2, RS2> Copy for ComposedView<'_, A, B, C, LS1, RS1, LS2, RS2>
+where
+    LS1: Side,
+    RS1: Side,
+    LS2: Side,
+    RS2: Side,
+{
+}
+
+impl<'a, A, B, C: 'a, LS1: Side, LS2: SideKey<B>> Iterator
+    for ComposedPairs<'a, A, B, C, LS1, LS2>
+{
+    type Item = (&'a A, &'a C);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (second, slab) = (self.second, self.slab);
+        self.first.find_map(|(a, b)| {
+            let id = LS2::find(second, slab.by_left(), b)?;
+            Some((a, slab.right(id)))
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.first.size_hint().1)
+    }
+}
+
+impl<'a, A, B, C: 'a, LS1: Side, LS2: SideKey<B>> FusedIterator
+    for ComposedPairs<'a, A, B, C, LS1, LS2>
+{
+}
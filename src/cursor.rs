@@ -0,0 +1,228 @@
+use std::borrow::Borrow;
+use std::ops::Bound;
+
+use crate::invariants::InvariantViolation;
+use crate::side::{Ordered, Side, SideKey};
+use crate::slab::{Id, Slab};
+use crate::tree::Tree;
+use crate::two_way_map::TwoWayMap;
+
+// A cursor sits in the gap between two pairs of one `Ordered` index, like the cursors of
+// `BTreeMap`. It remembers the slot after the gap (none at the end) and steps to the slot
+// before it through the links of the tree, so moving compares nothing.
+//
+// The mutable cursors hold the map instead of the tree, and the slot id stays valid for as
+// long as they borrow it: only the cursor itself can take a pair out in the meantime.
+
+pub struct LeftCursor<'a, L, R> {
+    slab: &'a Slab<L, R>,
+    tree: &'a Tree,
+    next: Option<Id>,
+}
+
+pub struct RightCursor<'a, L, R> {
+    slab: &'a Slab<L, R>,
+    tree: &'a Tree,
+    next: Option<Id>,
+}
+
+pub struct LeftCursorMut<'a, L, R, RS: Side = Ordered> {
+    map: &'a mut TwoWayMap<L, R, Ordered, RS>,
+    next: Option<Id>,
+}
+
+pub struct RightCursorMut<'a, L, R, LS: Side = Ordered> {
+    map: &'a mut TwoWayMap<L, R, LS, Ordered>,
+    next: Option<Id>,
+}
+
+fn before(tree: &Tree, next: Option<Id>) -> Option<Id> {
+    match next {
+        Some(next) => tree.prev(next),
+        None => tree.last(),
+    }
+}
+
+impl<L: Ord, R, RS: Side> TwoWayMap<L, R, Ordered, RS> {
+    // A cursor before the first pair whose left value is above `bound`
+    pub fn left_cursor_at<Q>(&self, bound: Bound<&Q>) -> LeftCursor<'_, L, R>
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        LeftCursor {
+            slab: &self.slab,
+            tree: &self.left_to_right,
+            next: self.left_to_right.lower_bound(self.slab.by_left(), bound),
+        }
+    }
+}
+
+impl<L: Ord, R, RS: SideKey<R>> TwoWayMap<L, R, Ordered, RS> {
+    pub fn left_cursor_mut_at<Q>(&mut self, bound: Bound<&Q>) -> LeftCursorMut<'_, L, R, RS>
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let next = self.left_to_right.lower_bound(self.slab.by_left(), bound);
+        LeftCursorMut { map: self, next }
+    }
+}
+
+impl<L, R: Ord, LS: Side> TwoWayMap<L, R, LS, Ordered> {
+    // A cursor before the first pair whose right value is above `bound`
+    pub fn right_cursor_at<Q>(&self, bound: Bound<&Q>) -> RightCursor<'_, L, R>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        RightCursor {
+            slab: &self.slab,
+            tree: &self.right_to_left,
+            next: self.right_to_left.lower_bound(self.slab.by_right(), bound),
+        }
+    }
+}
+
+impl<L, R: Ord, LS: SideKey<L>> TwoWayMap<L, R, LS, Ordered> {
+    pub fn right_cursor_mut_at<Q>(&mut self, bound: Bound<&Q>) -> RightCursorMut<'_, L, R, LS>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let next = self.right_to_left.lower_bound(self.slab.by_right(), bound);
+        RightCursorMut { map: self, next }
+    }
+}
+
+impl<'a, L, R> LeftCursor<'a, L, R> {
+    pub fn peek_next(&self) -> Option<(&'a L, &'a R)> {
+        self.next.map(|id| self.slab.pair(id))
+    }
+
+    pub fn peek_prev(&self) -> Option<(&'a L, &'a R)> {
+        before(self.tree, self.next).map(|id| self.slab.pair(id))
+    }
+
+    // Moves past the pair after the cursor and returns it
+    pub fn move_next(&mut self) -> Option<(&'a L, &'a R)> {
+        let id = self.next?;
+        self.next = self.tree.next(id);
+        Some(self.slab.pair(id))
+    }
+
+    // Moves back past the pair before the cursor and returns it
+    pub fn move_prev(&mut self) -> Option<(&'a L, &'a R)> {
+        let id = before(self.tree, self.next)?;
+        self.next = Some(id);
+        Some(self.slab.pair(id))
+    }
+}
+
+impl<'a, L, R> RightCursor<'a, L, R> {
+    pub fn peek_next(&self) -> Option<(&'a R, &'a L)> {
+        self.next.map(|id| swap(self.slab.pair(id)))
+    }
+
+    pub fn peek_prev(&self) -> Option<(&'a R, &'a L)> {
+        before(self.tree, self.next).map(|id| swap(self.slab.pair(id)))
+    }
+
+    pub fn move_next(&mut self) -> Option<(&'a R, &'a L)> {
+        let id = self.next?;
+        self.next = self.tree.next(id);
+        Some(swap(self.slab.pair(id)))
+    }
+
+    pub fn move_prev(&mut self) -> Option<(&'a R, &'a L)> {
+        let id = before(self.tree, self.next)?;
+        self.next = Some(id);
+        Some(swap(self.slab.pair(id)))
+    }
+}
+
+impl<L: Ord, R, RS: SideKey<R>> LeftCursorMut<'_, L, R, RS> {
+    pub fn peek_next(&self) -> Option<(&L, &R)> {
+        self.next.map(|id| self.map.slab.pair(id))
+    }
+
+    pub fn peek_prev(&self) -> Option<(&L, &R)> {
+        let id = before(&self.map.left_to_right, self.next)?;
+        Some(self.map.slab.pair(id))
+    }
+
+    pub fn move_next(&mut self) -> Option<(&L, &R)> {
+        let id = self.next?;
+        self.next = self.map.left_to_right.next(id);
+        Some(self.map.slab.pair(id))
+    }
+
+    pub fn move_prev(&mut self) -> Option<(&L, &R)> {
+        let id = before(&self.map.left_to_right, self.next)?;
+        self.next = Some(id);
+        Some(self.map.slab.pair(id))
+    }
+
+    // Removes the pair after the cursor, the one `peek_next` returns, from both indexes.
+    // The cursor stays in place, so the pair that followed it is next now.
+    pub fn remove_current(&mut self) -> Option<(L, R)> {
+        self.try_remove_current()
+            .unwrap_or_else(|violation| panic!("two_way_map: {violation}"))
+    }
+
+    // Like `remove_current`, but if the indexes don't agree about the pair it reports how
+    // and leaves both the pair and the cursor where they were
+    pub fn try_remove_current(&mut self) -> Result<Option<(L, R)>, InvariantViolation> {
+        let Some(id) = self.next else {
+            return Ok(None);
+        };
+        let after = self.map.left_to_right.next(id);
+        let pair = self.map.take_found_by_left(id)?;
+        self.next = after;
+        self.map.debug_check_len();
+        Ok(Some(pair))
+    }
+}
+
+impl<L, R: Ord, LS: SideKey<L>> RightCursorMut<'_, L, R, LS> {
+    pub fn peek_next(&self) -> Option<(&R, &L)> {
+        self.next.map(|id| swap(self.map.slab.pair(id)))
+    }
+
+    pub fn peek_prev(&self) -> Option<(&R, &L)> {
+        let id = before(&self.map.right_to_left, self.next)?;
+        Some(swap(self.map.slab.pair(id)))
+    }
+
+    pub fn move_next(&mut self) -> Option<(&R, &L)> {
+        let id = self.next?;
+        self.next = self.map.right_to_left.next(id);
+        Some(swap(self.map.slab.pair(id)))
+    }
+
+    pub fn move_prev(&mut self) -> Option<(&R, &L)> {
+        let id = before(&self.map.right_to_left, self.next)?;
+        self.next = Some(id);
+        Some(swap(self.map.slab.pair(id)))
+    }
+
+    pub fn remove_current(&mut self) -> Option<(R, L)> {
+        self.try_remove_current()
+            .unwrap_or_else(|violation| panic!("two_way_map: {violation}"))
+    }
+
+    pub fn try_remove_current(&mut self) -> Result<Option<(R, L)>, InvariantViolation> {
+        let Some(id) = self.next else {
+            return Ok(None);
+        };
+        let after = self.map.right_to_left.next(id);
+        let (left, right) = self.map.take_found_by_right(id)?;
+        self.next = after;
+        self.map.debug_check_len();
+        Ok(Some((right, left)))
+    }
+}
+
+fn swap<'a, L, R>((left, right): (&'a L, &'a R)) -> (&'a R, &'a L) {
+    (right, left)
+}
@@ -0,0 +1,201 @@
+use crate::side::{Ordered, Side, SideKey};
+use crate::slab::Id;
+use crate::two_way_map::TwoWayMap;
+
+// An occupied entry keeps the slot its key was found in, so reading or removing the pair
+// doesn't look it up again
+
+pub enum LeftEntry<'a, L, R, LS: Side = Ordered, RS: Side = Ordered> {
+    Occupied(OccupiedLeftEntry<'a, L, R, LS, RS>),
+    Vacant(VacantLeftEntry<'a, L, R, LS, RS>),
+}
+
+pub struct OccupiedLeftEntry<'a, L, R, LS: Side = Ordered, RS: Side = Ordered> {
+    map: &'a mut TwoWayMap<L, R, LS, RS>,
+    left: L,
+    id: Id,
+}
+
+pub struct VacantLeftEntry<'a, L, R, LS: Side = Ordered, RS: Side = Ordered> {
+    map: &'a mut TwoWayMap<L, R, LS, RS>,
+    left: L,
+}
+
+pub enum RightEntry<'a, L, R, LS: Side = Ordered, RS: Side = Ordered> {
+    Occupied(OccupiedRightEntry<'a, L, R, LS, RS>),
+    Vacant(VacantRightEntry<'a, L, R, LS, RS>),
+}
+
+pub struct OccupiedRightEntry<'a, L, R, LS: Side = Ordered, RS: Side = Ordered> {
+    map: &'a mut TwoWayMap<L, R, LS, RS>,
+    right: R,
+    id: Id,
+}
+
+pub struct VacantRightEntry<'a, L, R, LS: Side = Ordered, RS: Side = Ordered> {
+    map: &'a mut TwoWayMap<L, R, LS, RS>,
+    right: R,
+}
+
+impl<L, R, LS: SideKey<L>, RS: SideKey<R>> TwoWayMap<L, R, LS, RS> {
+    pub fn left_entry(&mut self, left: L) -> LeftEntry<'_, L, R, LS, RS> {
+        match LS::find(&self.left_to_right, self.slab.by_left(), &left) {
+            Some(id) => LeftEntry::Occupied(OccupiedLeftEntry {
+                map: self,
+                left,
+                id,
+            }),
+            None => LeftEntry::Vacant(VacantLeftEntry { map: self, left }),
+        }
+    }
+
+    pub fn right_entry(&mut self, right: R) -> RightEntry<'_, L, R, LS, RS> {
+        match RS::find(&self.right_to_left, self.slab.by_right(), &right) {
+            Some(id) => RightEntry::Occupied(OccupiedRightEntry {
+                map: self,
+                right,
+                id,
+            }),
+            None => RightEntry::Vacant(VacantRightEntry { map: self, right }),
+        }
+    }
+}
+
+impl<'a, L, R, LS: SideKey<L>, RS: SideKey<R>> LeftEntry<'a, L, R, LS, RS> {
+    pub fn left(&self) -> &L {
+        match self {
+            LeftEntry::Occupied(entry) => entry.left(),
+            LeftEntry::Vacant(entry) => entry.left(),
+        }
+    }
+
+    // Returns the right value already mapped to the left one, or maps `right` to it.
+    // Fails with the rejected pair if `right` is already mapped to another left value.
+    pub fn or_insert(self, right: R) -> Result<&'a R, (L, R)> {
+        match self {
+            LeftEntry::Occupied(entry) => Ok(entry.into_ref()),
+            LeftEntry::Vacant(entry) => entry.insert(right),
+        }
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> R>(self, f: F) -> Result<&'a R, (L, R)> {
+        match self {
+            LeftEntry::Occupied(entry) => Ok(entry.into_ref()),
+            LeftEntry::Vacant(entry) => entry.insert(f()),
+        }
+    }
+}
+
+impl<'a, L, R, LS: SideKey<L>, RS: SideKey<R>> OccupiedLeftEntry<'a, L, R, LS, RS> {
+    pub fn left(&self) -> &L {
+        &self.left
+    }
+
+    pub fn get(&self) -> &R {
+        self.map.slab.right(self.id)
+    }
+
+    pub fn into_ref(self) -> &'a R {
+        let map: &'a TwoWayMap<L, R, LS, RS> = self.map;
+        map.slab.right(self.id)
+    }
+
+    pub fn remove(self) -> (L, R) {
+        let pair = self
+            .map
+            .take_found_by_left(self.id)
+            .unwrap_or_else(|violation| panic!("two_way_map: {violation}"));
+        self.map.debug_check_len();
+        pair
+    }
+}
+
+impl<'a, L, R, LS: SideKey<L>, RS: SideKey<R>> VacantLeftEntry<'a, L, R, LS, RS> {
+    pub fn left(&self) -> &L {
+        &self.left
+    }
+
+    pub fn into_left(self) -> L {
+        self.left
+    }
+
+    // Fails if `right` is already mapped, which adding the pair to the right index finds out
+    pub fn insert(self, right: R) -> Result<&'a R, (L, R)> {
+        let map = self.map;
+        let id = map.try_attach(self.left, right)?;
+        map.debug_check_len();
+
+        let map: &'a TwoWayMap<L, R, LS, RS> = map;
+        Ok(map.slab.right(id))
+    }
+}
+
+impl<'a, L, R, LS: SideKey<L>, RS: SideKey<R>> RightEntry<'a, L, R, LS, RS> {
+    pub fn right(&self) -> &R {
+        match self {
+            RightEntry::Occupied(entry) => entry.right(),
+            RightEntry::Vacant(entry) => entry.right(),
+        }
+    }
+
+    // Returns the left value already mapped to the right one, or maps `left` to it.
+    // Fails with the rejected pair if `left` is already mapped to another right value.
+    pub fn or_insert(self, left: L) -> Result<&'a L, (R, L)> {
+        match self {
+            RightEntry::Occupied(entry) => Ok(entry.into_ref()),
+            RightEntry::Vacant(entry) => entry.insert(left),
+        }
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> L>(self, f: F) -> Result<&'a L, (R, L)> {
+        match self {
+            RightEntry::Occupied(entry) => Ok(entry.into_ref()),
+            RightEntry::Vacant(entry) => entry.insert(f()),
+        }
+    }
+}
+
+impl<'a, L, R, LS: SideKey<L>, RS: SideKey<R>> OccupiedRightEntry<'a, L, R, LS, RS> {
+    pub fn right(&self) -> &R {
+        &self.right
+    }
+
+    pub fn get(&self) -> &L {
+        self.map.slab.left(self.id)
+    }
+
+    pub fn into_ref(self) -> &'a L {
+        let map: &'a TwoWayMap<L, R, LS, RS> = self.map;
+        map.slab.left(self.id)
+    }
+
+    pub fn remove(self) -> (R, L) {
+        let (left, right) = self
+            .map
+            .take_found_by_right(self.id)
+            .unwrap_or_else(|violation| panic!("two_way_map: {violation}"));
+        self.map.debug_check_len();
+        (right, left)
+    }
+}
+
+impl<'a, L, R, LS: SideKey<L>, RS: SideKey<R>> VacantRightEntry<'a, L, R, LS, RS> {
+    pub fn right(&self) -> &R {
+        &self.right
+    }
+
+    pub fn into_right(self) -> R {
+        self.right
+    }
+
+    pub fn insert(self, left: L) -> Result<&'a L, (R, L)> {
+        let map = self.map;
+        let id = map
+            .try_attach(left, self.right)
+            .map_err(|(left, right)| (right, left))?;
+        map.debug_check_len();
+
+        let map: &'a TwoWayMap<L, R, LS, RS> = map;
+        Ok(map.slab.left(id))
+    }
+}
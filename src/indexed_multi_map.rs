@@ -0,0 +1,276 @@
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Debug};
+use std::ops::RangeBounds;
+
+use crate::bi_multi_map::{Index, attach_pair, detach_pair, groups_in};
+use crate::iter::{
+    IndexedPairsByRight, IndexedRightRange, MultiLeftRange, MultiPairs, RightsByLeft,
+};
+use crate::mem::{ByLeft, ByRight, Pair, wrap_range, wrap_ref};
+use crate::unwind::Undo;
+
+// A one-to-many map from parents to children: a left value owns any number of right values,
+// and each right value has exactly one owner. The left index groups the pairs of each left
+// value like in `BiMultiMap`, ordered by their right values, and the right index holds one
+// handle per right value like in `TwoWayMap`.
+//
+// A panicking `Ord` implementation leaves the map as it was before `insert`, `reparent`,
+// `remove_by_right` or `remove_left_cascade` were called.
+pub struct IndexedMultiMap<L, R> {
+    left_to_right: Index<L, R, ByLeft, ByRight>,
+    right_to_left: BTreeSet<Pair<L, R, ByRight>>,
+}
+
+impl<L, R> IndexedMultiMap<L, R> {
+    pub fn new() -> Self {
+        Self {
+            left_to_right: BTreeMap::new(),
+            right_to_left: BTreeSet::new(),
+        }
+    }
+
+    // The number of pairs, which is also the number of right values
+    pub fn len(&self) -> usize {
+        self.right_to_left.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.right_to_left.is_empty()
+    }
+
+    // The number of left values that own at least one right value
+    pub fn left_len(&self) -> usize {
+        self.left_to_right.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.left_to_right.clear();
+        self.right_to_left.clear();
+    }
+
+    // In the order of the left values, and of the right values each of them owns
+    pub fn pairs(&self) -> MultiPairs<'_, L, R> {
+        MultiPairs::new(self.left_to_right.values().flatten())
+    }
+
+    pub fn pairs_by_right(&self) -> IndexedPairsByRight<'_, L, R> {
+        IndexedPairsByRight::new(self.right_to_left.iter())
+    }
+}
+
+impl<L: Ord, R: Ord> IndexedMultiMap<L, R> {
+    // Fails, giving both values back, if `right` already has an owner, even if it's `left`
+    pub fn insert(&mut self, left: L, right: R) -> Result<(), (L, R)> {
+        if self.contains_right(&right) {
+            return Err((left, right));
+        }
+        self.attach(Pair::new(left, right));
+        Ok(())
+    }
+
+    fn attach(&mut self, pair: Pair<L, R, ByRight>) {
+        attach_pair(
+            &mut self.right_to_left,
+            put_back,
+            |set, pair| drop(set.take(wrap_ref(pair.right()))),
+            &mut self.left_to_right,
+            pair,
+        );
+    }
+
+    // The right values `left` owns, in order
+    pub fn get_by_left<Q>(&self, left: &Q) -> RightsByLeft<'_, L, R>
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let group = self.left_to_right.get(wrap_ref(left));
+        RightsByLeft::new(group.map(BTreeSet::iter).unwrap_or_default())
+    }
+
+    // The owner of `right`
+    pub fn get_by_right<Q>(&self, right: &Q) -> Option<&L>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.right_to_left.get(wrap_ref(right)).map(Pair::left)
+    }
+
+    pub fn contains_left<Q>(&self, left: &Q) -> bool
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.left_to_right.contains_key(wrap_ref(left))
+    }
+
+    pub fn contains_right<Q>(&self, right: &Q) -> bool
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.right_to_left.contains(wrap_ref(right))
+    }
+
+    pub fn count_by_left<Q>(&self, left: &Q) -> usize
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.left_to_right
+            .get(wrap_ref(left))
+            .map_or(0, BTreeSet::len)
+    }
+
+    // Gives `right` the owner `new_left` and returns its old owner. Fails, giving `new_left`
+    // back, if `right` has no owner.
+    pub fn reparent<Q>(&mut self, right: &Q, new_left: L) -> Result<L, L>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let Some((right, old_left)) = self.remove_by_right(right) else {
+            return Err(new_left);
+        };
+        let pair = Pair::new(new_left, right);
+        // If adding the pair under `new_left` panics, the handle kept here is the last one
+        // left, and the right value goes back to its old owner
+        let mut kept = Undo::new(
+            self,
+            (old_left, pair.share::<ByRight>()),
+            |map, (old_left, pair)| {
+                let (_, right) = Pair::into_values(pair);
+                map.attach(Pair::new(old_left, right));
+            },
+        );
+        kept.target().attach(pair);
+        let (old_left, _) = kept.done();
+        Ok(old_left)
+    }
+
+    pub fn remove_by_right<Q>(&mut self, right: &Q) -> Option<(R, L)>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let pair = self.right_to_left.take(wrap_ref(right))?;
+        let (left, right) = detach_pair(
+            &mut self.right_to_left,
+            pair,
+            put_back,
+            &mut self.left_to_right,
+        );
+        Some((right, left))
+    }
+
+    // Removes `left` along with every right value it owns, returning the pairs in the order
+    // of their right values
+    pub fn remove_left_cascade<Q>(&mut self, left: &Q) -> Vec<(L, R)>
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let Some((_, group)) = self.left_to_right.remove_entry(wrap_ref(left)) else {
+            return Vec::new();
+        };
+        // Put the group back if taking the handles out of the right index panics, along
+        // with the handles taken so far
+        let mut guard = Undo::new(self, (group, Vec::new()), |map, (group, taken)| {
+            map.right_to_left.extend(taken);
+            let key = group.first().unwrap().share();
+            map.left_to_right.insert(key, group);
+        });
+        let (map, (group, taken)) = guard.split();
+        for pair in group.iter() {
+            taken.push(map.right_to_left.take(wrap_ref(pair.right())).unwrap());
+        }
+        let (group, _) = guard.done();
+
+        group.into_iter().map(Pair::into_values).collect()
+    }
+
+    // The pairs whose left value is in `range`, in the same order as `pairs`
+    pub fn left_range<Q>(&self, range: impl RangeBounds<Q>) -> MultiLeftRange<'_, L, R>
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        MultiLeftRange::new(groups_in(&self.left_to_right, &range))
+    }
+
+    pub fn right_range<Q>(&self, range: impl RangeBounds<Q>) -> IndexedRightRange<'_, L, R>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        IndexedRightRange::new(self.right_to_left.range(wrap_range(&range)))
+    }
+}
+
+// Puts a handle into the right index, which has none with the same right value
+fn put_back<L, R: Ord>(set: &mut BTreeSet<Pair<L, R, ByRight>>, pair: Pair<L, R, ByRight>) {
+    set.insert(pair);
+}
+
+impl<L, R> Default for IndexedMultiMap<L, R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L: Ord + Clone, R: Ord + Clone> Clone for IndexedMultiMap<L, R> {
+    fn clone(&self) -> Self {
+        let mut other = IndexedMultiMap::new();
+        for (left, right) in self.pairs() {
+            other.attach(Pair::new(left.clone(), right.clone()));
+        }
+        other
+    }
+}
+
+impl<L: Debug, R: Debug> Debug for IndexedMultiMap<L, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.pairs()).finish()
+    }
+}
+
+impl<L: PartialEq, R: PartialEq> PartialEq for IndexedMultiMap<L, R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.pairs().eq(other.pairs())
+    }
+}
+
+impl<L: Eq, R: Eq> Eq for IndexedMultiMap<L, R> {}
+
+// A right value that comes again is moved to the left value it comes with, the same way
+// `TwoWayMap` keeps the last pair for a value
+impl<L: Ord, R: Ord> Extend<(L, R)> for IndexedMultiMap<L, R> {
+    fn extend<T: IntoIterator<Item = (L, R)>>(&mut self, iter: T) {
+        for (left, right) in iter {
+            if let Err((left, right)) = self.insert(left, right) {
+                self.remove_by_right(&right);
+                self.attach(Pair::new(left, right));
+            }
+        }
+    }
+}
+
+impl<L: Ord, R: Ord> FromIterator<(L, R)> for IndexedMultiMap<L, R> {
+    fn from_iter<T: IntoIterator<Item = (L, R)>>(iter: T) -> Self {
+        let mut map = IndexedMultiMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<'a, L, R> IntoIterator for &'a IndexedMultiMap<L, R> {
+    type Item = (&'a L, &'a R);
+
+    type IntoIter = MultiPairs<'a, L, R>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pairs()
+    }
+}
@@ -0,0 +1,103 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::side::{Ids, Side, SideIndex, SideKey};
+use crate::slab::Id;
+use crate::two_way_map::TwoWayMap;
+
+// What `check_invariants` found wrong with the two indexes of a map. Each pair lives in a
+// slot of its own, and both indexes must hold that slot exactly once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvariantViolation {
+    // The indexes hold a different number of pairs
+    LengthMismatch { left: usize, right: usize },
+    // A pair of one index can't be found in the other one by its value on that side
+    DanglingPartner,
+    // Looking the pair up in the other index leads to a different slot
+    NotShared,
+    // An index holds a slot that has no pair in it
+    VacantSlot,
+}
+
+impl Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvariantViolation::LengthMismatch { left, right } => write!(
+                f,
+                "the left index holds {left} pairs but the right index holds {right}"
+            ),
+            InvariantViolation::DanglingPartner => {
+                write!(f, "a pair is missing from one of the indexes")
+            }
+            InvariantViolation::NotShared => {
+                write!(f, "the indexes lead to different slots for one pair")
+            }
+            InvariantViolation::VacantSlot => {
+                write!(f, "an index holds a slot that has no pair in it")
+            }
+        }
+    }
+}
+
+impl Error for InvariantViolation {}
+
+impl<L, R, LS: SideKey<L>, RS: SideKey<R>> TwoWayMap<L, R, LS, RS> {
+    // Walks the left index and looks every pair up in the right one. With equal lengths
+    // that also covers every slot of the right index.
+    pub fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        let left = self.left_to_right.len();
+        let right = self.right_to_left.len();
+        if left != right {
+            return Err(InvariantViolation::LengthMismatch { left, right });
+        }
+
+        for id in Ids::new(&self.left_to_right) {
+            self.check_partner_by_left(id)?;
+        }
+        Ok(())
+    }
+
+    // Whether the pair in slot `id`, found through one index, leads back to that slot when
+    // it's looked up in the other one by its value on that side
+    pub(crate) fn check_partner_by_left(&self, id: Id) -> Result<(), InvariantViolation> {
+        let (_, right) = self.slab.get(id).ok_or(InvariantViolation::VacantSlot)?;
+        match RS::find(&self.right_to_left, self.slab.by_right(), right) {
+            None => Err(InvariantViolation::DanglingPartner),
+            Some(partner) if partner != id => Err(InvariantViolation::NotShared),
+            Some(_) => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_partner_by_right(&self, id: Id) -> Result<(), InvariantViolation> {
+        let (left, _) = self.slab.get(id).ok_or(InvariantViolation::VacantSlot)?;
+        match LS::find(&self.left_to_right, self.slab.by_left(), left) {
+            None => Err(InvariantViolation::DanglingPartner),
+            Some(partner) if partner != id => Err(InvariantViolation::NotShared),
+            Some(_) => Ok(()),
+        }
+    }
+
+    // Run after operations that touch many pairs at once, which already cost a full pass
+    pub(crate) fn debug_check(&self) {
+        #[cfg(debug_assertions)]
+        if let Err(violation) = self.check_invariants() {
+            panic!("two_way_map: {violation}");
+        }
+    }
+}
+
+impl<L, R, LS: Side, RS: Side> TwoWayMap<L, R, LS, RS> {
+    // Run after operations on a single pair, where a full pass would be too expensive
+    pub(crate) fn debug_check_len(&self) {
+        debug_assert_eq!(
+            self.left_to_right.len(),
+            self.right_to_left.len(),
+            "two_way_map: the indexes hold a different number of pairs"
+        );
+        debug_assert_eq!(
+            self.slab.len(),
+            self.left_to_right.len(),
+            "two_way_map: the slab holds a pair the indexes don't"
+        );
+    }
+}
@@ -0,0 +1,464 @@
+use std::borrow::Borrow;
+use std::fmt::{self, Debug};
+use std::ops::Bound;
+use std::ops::RangeBounds;
+
+use bytemuck::TransparentWrapper;
+
+use crate::cursor::{LeftCursor, LeftCursorMut, RightCursor, RightCursorMut};
+use crate::entry::{LeftEntry, RightEntry};
+use crate::invariants::InvariantViolation;
+use crate::iter::{
+    InversePairs, InversePairsByRight, LeftRange, LeftValues, RightRange, RightValues,
+};
+use crate::side::{Ids, Ordered, Side, SideKey, SideLookup};
+use crate::two_way_map::{Overwritten, ReplaceError, TwoWayMap};
+use crate::value_mut::{LeftMut, RightMut};
+
+// A `TwoWayMap<R, L>` seen from the other side: its left values are the right values of
+// the map and the other way around. It has the same layout as the map, so `as_inverse`
+// and `as_inverse_mut` only cast the reference. Methods forward to their counterpart for
+// the other side and swap what they return; some of the types they return, such as
+// entries and cursors, are the ones of that counterpart.
+#[derive(TransparentWrapper)]
+#[repr(transparent)]
+pub struct InverseView<L, R, LS: Side = Ordered, RS: Side = Ordered> {
+    map: TwoWayMap<R, L, RS, LS>,
+}
+
+impl<L, R, LS: Side, RS: Side> TwoWayMap<L, R, LS, RS> {
+    pub fn as_inverse(&self) -> &InverseView<R, L, RS, LS> {
+        InverseView::wrap_ref(self)
+    }
+
+    pub fn as_inverse_mut(&mut self) -> &mut InverseView<R, L, RS, LS> {
+        InverseView::wrap_mut(self)
+    }
+}
+
+impl<L, R, LS: Side, RS: Side> TwoWayMap<L, R, LS, RS> {
+    // The slab swaps its vectors of left and right values, so every pair is swapped in
+    // place and the ids stay the same. The old indexes serve as they are, the right one
+    // becoming the left one. O(1): nothing is moved, compared, hashed or allocated.
+    pub fn into_inverse(self) -> TwoWayMap<R, L, RS, LS> {
+        let TwoWayMap {
+            slab,
+            left_to_right,
+            right_to_left,
+        } = self;
+        let map = TwoWayMap {
+            slab: slab.inverse(),
+            left_to_right: right_to_left,
+            right_to_left: left_to_right,
+        };
+        map.debug_check_len();
+        map
+    }
+}
+
+impl<L, R> Overwritten<L, R> {
+    // The same displaced pairs, as seen from an `InverseView`
+    pub(crate) fn inverse(self) -> Overwritten<R, L> {
+        match self {
+            Overwritten::Neither => Overwritten::Neither,
+            Overwritten::Left(left, right) => Overwritten::Right(right, left),
+            Overwritten::Right(left, right) => Overwritten::Left(right, left),
+            Overwritten::Pair(left, right) => Overwritten::Pair(right, left),
+            Overwritten::Both((l1, r1), (l2, r2)) => Overwritten::Both((r2, l2), (r1, l1)),
+        }
+    }
+}
+
+impl<L, R, LS: Side, RS: Side> InverseView<L, R, LS, RS> {
+    // The map this is a view of
+    pub fn as_inverse(&self) -> &TwoWayMap<R, L, RS, LS> {
+        &self.map
+    }
+
+    pub fn as_inverse_mut(&mut self) -> &mut TwoWayMap<R, L, RS, LS> {
+        &mut self.map
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    pub fn pairs(&self) -> InversePairs<'_, L, R, LS> {
+        InversePairs::new(&self.map.slab, Ids::new(&self.map.right_to_left))
+    }
+
+    pub fn pairs_by_right(&self) -> InversePairsByRight<'_, L, R, RS> {
+        InversePairsByRight::new(&self.map.slab, Ids::new(&self.map.left_to_right))
+    }
+
+    pub fn left_values(&self) -> RightValues<'_, R, L, LS> {
+        self.map.right_values()
+    }
+
+    pub fn right_values(&self) -> LeftValues<'_, R, L, RS> {
+        self.map.left_values()
+    }
+}
+
+impl<L, R, LS: SideKey<L>, RS: SideKey<R>> InverseView<L, R, LS, RS> {
+    pub fn insert(&mut self, left: L, right: R) -> Overwritten<L, R> {
+        self.map.insert(right, left).inverse()
+    }
+
+    pub fn insert_no_overwrite(&mut self, left: L, right: R) -> Result<(), (L, R)> {
+        self.map
+            .insert_no_overwrite(right, left)
+            .map_err(|(right, left)| (left, right))
+    }
+
+    pub fn replace_left<Q>(&mut self, old_left: &Q, new_left: L) -> Result<L, ReplaceError<L>>
+    where
+        LS: SideLookup<L, Q>,
+        Q: ?Sized,
+    {
+        self.map.replace_right(old_left, new_left)
+    }
+
+    pub fn replace_right<Q>(&mut self, old_right: &Q, new_right: R) -> Result<R, ReplaceError<R>>
+    where
+        RS: SideLookup<R, Q>,
+        Q: ?Sized,
+    {
+        self.map.replace_left(old_right, new_right)
+    }
+
+    pub fn remove_by_left<Q>(&mut self, left: &Q) -> Option<(L, R)>
+    where
+        LS: SideLookup<L, Q>,
+        Q: ?Sized,
+    {
+        self.map.remove_by_right(left)
+    }
+
+    pub fn remove_by_right<Q>(&mut self, right: &Q) -> Option<(R, L)>
+    where
+        RS: SideLookup<R, Q>,
+        Q: ?Sized,
+    {
+        self.map.remove_by_left(right)
+    }
+
+    pub fn try_remove_by_left<Q>(&mut self, left: &Q) -> Result<Option<(L, R)>, InvariantViolation>
+    where
+        LS: SideLookup<L, Q>,
+        Q: ?Sized,
+    {
+        self.map.try_remove_by_right(left)
+    }
+
+    pub fn try_remove_by_right<Q>(
+        &mut self,
+        right: &Q,
+    ) -> Result<Option<(R, L)>, InvariantViolation>
+    where
+        RS: SideLookup<R, Q>,
+        Q: ?Sized,
+    {
+        self.map.try_remove_by_left(right)
+    }
+
+    pub fn get_by_left<Q>(&self, left: &Q) -> Option<&R>
+    where
+        LS: SideLookup<L, Q>,
+        Q: ?Sized,
+    {
+        self.map.get_by_right(left)
+    }
+
+    pub fn get_by_right<Q>(&self, right: &Q) -> Option<&L>
+    where
+        RS: SideLookup<R, Q>,
+        Q: ?Sized,
+    {
+        self.map.get_by_left(right)
+    }
+
+    pub fn contains_left<Q>(&self, left: &Q) -> bool
+    where
+        LS: SideLookup<L, Q>,
+        Q: ?Sized,
+    {
+        self.map.contains_right(left)
+    }
+
+    pub fn contains_right<Q>(&self, right: &Q) -> bool
+    where
+        RS: SideLookup<R, Q>,
+        Q: ?Sized,
+    {
+        self.map.contains_left(right)
+    }
+
+    pub fn get_by_left_mut<Q>(&mut self, left: &Q) -> Option<LeftMut<'_, R, L, RS, LS>>
+    where
+        LS: SideLookup<L, Q>,
+        Q: ?Sized,
+    {
+        self.map.get_by_right_mut(left)
+    }
+
+    pub fn get_by_right_mut<Q>(&mut self, right: &Q) -> Option<RightMut<'_, R, L, RS, LS>>
+    where
+        RS: SideLookup<R, Q>,
+        Q: ?Sized,
+    {
+        self.map.get_by_left_mut(right)
+    }
+
+    pub fn left_entry(&mut self, left: L) -> RightEntry<'_, R, L, RS, LS> {
+        self.map.right_entry(left)
+    }
+
+    pub fn right_entry(&mut self, right: R) -> LeftEntry<'_, R, L, RS, LS> {
+        self.map.left_entry(right)
+    }
+
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&L, &R) -> bool,
+    {
+        self.map.retain_by_right(|right, left| f(left, right));
+    }
+
+    pub fn retain_by_right<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&L, &R) -> bool,
+    {
+        self.map.retain(|right, left| f(left, right));
+    }
+
+    pub fn extract_if<F>(&mut self, mut f: F) -> Vec<(L, R)>
+    where
+        F: FnMut(&L, &R) -> bool,
+    {
+        self.map
+            .detach_by_right(|right, left| f(left, right))
+            .into_iter()
+            .map(|(right, left)| (left, right))
+            .collect()
+    }
+
+    pub fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        self.map.check_invariants()
+    }
+}
+
+impl<L: Ord, R, RS: Side> InverseView<L, R, Ordered, RS> {
+    pub fn left_range<T>(&self, range: T) -> RightRange<'_, R, L>
+    where
+        T: RangeBounds<L>,
+    {
+        self.map.right_range(range)
+    }
+
+    pub fn left_range_borrowed<'q, Q>(&self, range: impl RangeBounds<&'q Q>) -> RightRange<'_, R, L>
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized + 'q,
+    {
+        self.map.right_range_borrowed(range)
+    }
+
+    pub fn first_by_left(&self) -> Option<(&L, &R)> {
+        self.map.first_by_right().map(swap)
+    }
+
+    pub fn last_by_left(&self) -> Option<(&L, &R)> {
+        self.map.last_by_right().map(swap)
+    }
+
+    pub fn floor_left<Q>(&self, left: &Q) -> Option<(&L, &R)>
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.floor_right(left).map(swap)
+    }
+
+    pub fn ceiling_left<Q>(&self, left: &Q) -> Option<(&L, &R)>
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.ceiling_right(left).map(swap)
+    }
+
+    pub fn lower_left<Q>(&self, left: &Q) -> Option<(&L, &R)>
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.lower_right(left).map(swap)
+    }
+
+    pub fn higher_left<Q>(&self, left: &Q) -> Option<(&L, &R)>
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.higher_right(left).map(swap)
+    }
+
+    pub fn left_cursor_at<Q>(&self, bound: Bound<&Q>) -> RightCursor<'_, R, L>
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.right_cursor_at(bound)
+    }
+}
+
+impl<L: Ord, R, RS: SideKey<R>> InverseView<L, R, Ordered, RS> {
+    pub fn pop_first_by_left(&mut self) -> Option<(L, R)> {
+        self.map.pop_first_by_right()
+    }
+
+    pub fn pop_last_by_left(&mut self) -> Option<(L, R)> {
+        self.map.pop_last_by_right()
+    }
+
+    pub fn try_pop_first_by_left(&mut self) -> Result<Option<(L, R)>, InvariantViolation> {
+        self.map.try_pop_first_by_right()
+    }
+
+    pub fn try_pop_last_by_left(&mut self) -> Result<Option<(L, R)>, InvariantViolation> {
+        self.map.try_pop_last_by_right()
+    }
+
+    pub fn left_cursor_mut_at<Q>(&mut self, bound: Bound<&Q>) -> RightCursorMut<'_, R, L, RS>
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.right_cursor_mut_at(bound)
+    }
+}
+
+impl<L, R: Ord, LS: Side> InverseView<L, R, LS, Ordered> {
+    pub fn right_range<T>(&self, range: T) -> LeftRange<'_, R, L>
+    where
+        T: RangeBounds<R>,
+    {
+        self.map.left_range(range)
+    }
+
+    pub fn right_range_borrowed<'q, Q>(&self, range: impl RangeBounds<&'q Q>) -> LeftRange<'_, R, L>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized + 'q,
+    {
+        self.map.left_range_borrowed(range)
+    }
+
+    pub fn first_by_right(&self) -> Option<(&L, &R)> {
+        self.map.first_by_left().map(swap)
+    }
+
+    pub fn last_by_right(&self) -> Option<(&L, &R)> {
+        self.map.last_by_left().map(swap)
+    }
+
+    pub fn floor_right<Q>(&self, right: &Q) -> Option<(&L, &R)>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.floor_left(right).map(swap)
+    }
+
+    pub fn ceiling_right<Q>(&self, right: &Q) -> Option<(&L, &R)>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.ceiling_left(right).map(swap)
+    }
+
+    pub fn lower_right<Q>(&self, right: &Q) -> Option<(&L, &R)>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.lower_left(right).map(swap)
+    }
+
+    pub fn higher_right<Q>(&self, right: &Q) -> Option<(&L, &R)>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.higher_left(right).map(swap)
+    }
+
+    pub fn right_cursor_at<Q>(&self, bound: Bound<&Q>) -> LeftCursor<'_, R, L>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.left_cursor_at(bound)
+    }
+}
+
+impl<L, R: Ord, LS: SideKey<L>> InverseView<L, R, LS, Ordered> {
+    pub fn pop_first_by_right(&mut self) -> Option<(R, L)> {
+        self.map.pop_first_by_left()
+    }
+
+    pub fn pop_last_by_right(&mut self) -> Option<(R, L)> {
+        self.map.pop_last_by_left()
+    }
+
+    pub fn try_pop_first_by_right(&mut self) -> Result<Option<(R, L)>, InvariantViolation> {
+        self.map.try_pop_first_by_left()
+    }
+
+    pub fn try_pop_last_by_right(&mut self) -> Result<Option<(R, L)>, InvariantViolation> {
+        self.map.try_pop_last_by_left()
+    }
+
+    pub fn right_cursor_mut_at<Q>(&mut self, bound: Bound<&Q>) -> LeftCursorMut<'_, R, L, LS>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.left_cursor_mut_at(bound)
+    }
+}
+
+impl<L, R, LS: Side, RS: Side> Debug for InverseView<L, R, LS, RS>
+where
+    TwoWayMap<R, L, RS, LS>: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InverseView")
+            .field("map", &self.map)
+            .finish()
+    }
+}
+
+impl<'a, L, R, LS: Side, RS: Side> IntoIterator for &'a InverseView<L, R, LS, RS> {
+    type Item = (&'a L, &'a R);
+
+    type IntoIter = InversePairs<'a, L, R, LS>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pairs()
+    }
+}
+
+fn swap<'a, R, L>((right, left): (&'a R, &'a L)) -> (&'a L, &'a R) {
+    (left, right)
+}
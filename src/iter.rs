@@ -0,0 +1,492 @@
+use std::collections::{BTreeSet, btree_map, btree_set};
+use std::fmt::{self, Debug};
+use std::iter::{self, FusedIterator};
+
+use crate::invariants::InvariantViolation;
+use crate::mem::{ByLeft, ByRight, Pair};
+use crate::side::{Ids, Ordered, Side, SideIndex, TreeRange, Walk};
+use crate::slab::{Id, Slab};
+use crate::two_way_map::TwoWayMap;
+
+pub struct Pairs<'a, L: 'a, R: 'a, LS: Side + 'a = Ordered> {
+    slab: &'a Slab<L, R>,
+    iter: Ids<'a, LS::Index>,
+}
+
+pub struct PairsByRight<'a, L: 'a, R: 'a, RS: Side + 'a = Ordered> {
+    slab: &'a Slab<L, R>,
+    iter: Ids<'a, RS::Index>,
+}
+
+pub struct LeftValues<'a, L: 'a, R: 'a, LS: Side + 'a = Ordered> {
+    slab: &'a Slab<L, R>,
+    iter: Ids<'a, LS::Index>,
+}
+
+pub struct RightValues<'a, L: 'a, R: 'a, RS: Side + 'a = Ordered> {
+    slab: &'a Slab<L, R>,
+    iter: Ids<'a, RS::Index>,
+}
+
+pub struct LeftRange<'a, L: 'a, R: 'a> {
+    slab: &'a Slab<L, R>,
+    iter: TreeRange<'a>,
+}
+
+pub struct RightRange<'a, L: 'a, R: 'a> {
+    slab: &'a Slab<L, R>,
+    iter: TreeRange<'a>,
+}
+
+// The pairs of an `InverseView` in the order of its left or right index. `L` and `R` are
+// the types of the view, so the slots are those of the map it inverts, holding `(R, L)`.
+pub struct InversePairs<'a, L: 'a, R: 'a, LS: Side + 'a = Ordered> {
+    slab: &'a Slab<R, L>,
+    iter: Ids<'a, LS::Index>,
+}
+
+pub struct InversePairsByRight<'a, L: 'a, R: 'a, RS: Side + 'a = Ordered> {
+    slab: &'a Slab<R, L>,
+    iter: Ids<'a, RS::Index>,
+}
+
+// The pairs of a `BiMultiMap` in the order of its left or right index: the groups in the
+// order of their keys, and the pairs of each group in the order of their other value
+pub struct MultiPairs<'a, L: 'a, R: 'a> {
+    iter: Groups<'a, Pair<L, R, ByLeft>, Pair<L, R, ByRight>>,
+}
+
+pub struct MultiPairsByRight<'a, L: 'a, R: 'a> {
+    iter: Groups<'a, Pair<L, R, ByRight>, Pair<L, R, ByLeft>>,
+}
+
+pub struct MultiLeftRange<'a, L: 'a, R: 'a> {
+    iter: GroupRange<'a, Pair<L, R, ByLeft>, Pair<L, R, ByRight>>,
+}
+
+pub struct MultiRightRange<'a, L: 'a, R: 'a> {
+    iter: GroupRange<'a, Pair<L, R, ByRight>, Pair<L, R, ByLeft>>,
+}
+
+// The pairs of an `IndexedMultiMap` in the order of its right index, which holds one
+// handle per right value
+pub struct IndexedPairsByRight<'a, L: 'a, R: 'a> {
+    iter: btree_set::Iter<'a, Pair<L, R, ByRight>>,
+}
+
+pub struct IndexedRightRange<'a, L: 'a, R: 'a> {
+    iter: btree_set::Range<'a, Pair<L, R, ByRight>>,
+}
+
+// The values paired with one value of a `BiMultiMap`
+pub struct RightsByLeft<'a, L: 'a, R: 'a> {
+    iter: btree_set::Iter<'a, Pair<L, R, ByRight>>,
+}
+
+pub struct LeftsByRight<'a, L: 'a, R: 'a> {
+    iter: btree_set::Iter<'a, Pair<L, R, ByLeft>>,
+}
+
+type Groups<'a, K, T> = iter::Flatten<btree_map::Values<'a, K, BTreeSet<T>>>;
+
+pub(crate) type GroupRange<'a, K, T> = iter::FlatMap<
+    btree_map::Range<'a, K, BTreeSet<T>>,
+    &'a BTreeSet<T>,
+    fn((&'a K, &'a BTreeSet<T>)) -> &'a BTreeSet<T>,
+>;
+
+// Iterating over a borrowed map is the same as iterating over its pairs
+pub type RefIter<'a, L, R, LS = Ordered> = Pairs<'a, L, R, LS>;
+
+// The iterators over a borrowed map wrap an iterator over the slot ids of one index and
+// look each id up in the slab. They are all double-ended, and those over a whole index
+// know their length; ranges don't.
+macro_rules! slot_iter {
+    (
+        $name:ident $(<$side:ident>)?,
+        $slab:ty,
+        $inner:ty,
+        $item:ty,
+        |$pair:ident| $value:expr
+    ) => {
+        impl<'a, L: 'a, R: 'a, $($side: Side + 'a)?> $name<'a, L, R, $($side)?> {
+            pub(crate) fn new(slab: &'a $slab, iter: $inner) -> Self {
+                Self { slab, iter }
+            }
+        }
+
+        impl<'a, L: 'a, R: 'a, $($side: Side + 'a)?> Iterator for $name<'a, L, R, $($side)?> {
+            type Item = $item;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                let $pair = self.slab.pair(self.iter.next()?);
+                Some($value)
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.iter.size_hint()
+            }
+        }
+
+        impl<'a, L: 'a, R: 'a, $($side: Side + 'a)?> DoubleEndedIterator for $name<'a, L, R, $($side)?> {
+            fn next_back(&mut self) -> Option<Self::Item> {
+                let $pair = self.slab.pair(self.iter.next_back()?);
+                Some($value)
+            }
+        }
+
+        impl<'a, L: 'a, R: 'a, $($side: Side + 'a)?> ExactSizeIterator for $name<'a, L, R, $($side)?>
+        where
+            $inner: ExactSizeIterator,
+        {
+        }
+
+        impl<'a, L: 'a, R: 'a, $($side: Side + 'a)?> FusedIterator for $name<'a, L, R, $($side)?> {}
+
+        impl<'a, L: 'a, R: 'a, $($side: Side + 'a)?> Clone for $name<'a, L, R, $($side)?> {
+            fn clone(&self) -> Self {
+                Self {
+                    slab: self.slab,
+                    iter: self.iter.clone(),
+                }
+            }
+        }
+
+        impl<'a, L: 'a, R: 'a, $($side: Side + 'a)?> Debug for $name<'a, L, R, $($side)?>
+        where
+            $item: Debug,
+        {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let rest: Vec<_> = self.clone().collect();
+                f.debug_tuple(stringify!($name)).field(&rest).finish()
+            }
+        }
+    };
+}
+
+// The iterators over a `BiMultiMap` or an `IndexedMultiMap` wrap an iterator over handles
+// and map them to the values, so they implement the same iterator traits as the wrapped
+// iterator: ranges don't know their length
+macro_rules! borrowed_iter {
+    (
+        $name:ident $(<$side:ident>)?,
+        $inner:ty,
+        &'a $handle:ty,
+        $item:ty,
+        |$pair:ident| $value:expr
+    ) => {
+        impl<'a, L: 'a, R: 'a, $($side: Side + 'a)?> $name<'a, L, R, $($side)?> {
+            pub(crate) fn new(iter: $inner) -> Self {
+                Self { iter }
+            }
+        }
+
+        impl<'a, L: 'a, R: 'a, $($side: Side + 'a)?> Iterator for $name<'a, L, R, $($side)?> {
+            type Item = $item;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                self.iter.next().map(|$pair| $value)
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.iter.size_hint()
+            }
+        }
+
+        impl<'a, L: 'a, R: 'a, $($side: Side + 'a)?> DoubleEndedIterator for $name<'a, L, R, $($side)?>
+        where
+            $inner: DoubleEndedIterator<Item = &'a $handle>,
+        {
+            fn next_back(&mut self) -> Option<Self::Item> {
+                self.iter.next_back().map(|$pair| $value)
+            }
+        }
+
+        impl<'a, L: 'a, R: 'a, $($side: Side + 'a)?> ExactSizeIterator for $name<'a, L, R, $($side)?>
+        where
+            $inner: ExactSizeIterator<Item = &'a $handle>,
+        {
+        }
+
+        impl<'a, L: 'a, R: 'a, $($side: Side + 'a)?> FusedIterator for $name<'a, L, R, $($side)?>
+        where
+            $inner: FusedIterator<Item = &'a $handle>,
+        {
+        }
+
+        impl<'a, L: 'a, R: 'a, $($side: Side + 'a)?> Clone for $name<'a, L, R, $($side)?>
+        where
+            $inner: Clone,
+        {
+            fn clone(&self) -> Self {
+                Self {
+                    iter: self.iter.clone(),
+                }
+            }
+        }
+
+        impl<'a, L: 'a, R: 'a, $($side: Side + 'a)?> Debug for $name<'a, L, R, $($side)?>
+        where
+            $inner: Debug,
+        {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_tuple(stringify!($name)).field(&self.iter).finish()
+            }
+        }
+    };
+}
+
+slot_iter!(
+    Pairs<LS>,
+    Slab<L, R>,
+    Ids<'a, LS::Index>,
+    (&'a L, &'a R),
+    |pair| pair
+);
+slot_iter!(
+    PairsByRight<RS>,
+    Slab<L, R>,
+    Ids<'a, RS::Index>,
+    (&'a L, &'a R),
+    |pair| pair
+);
+slot_iter!(
+    LeftValues<LS>,
+    Slab<L, R>,
+    Ids<'a, LS::Index>,
+    &'a L,
+    |pair| pair.0
+);
+slot_iter!(
+    RightValues<RS>,
+    Slab<L, R>,
+    Ids<'a, RS::Index>,
+    &'a R,
+    |pair| pair.1
+);
+slot_iter!(
+    LeftRange,
+    Slab<L, R>,
+    TreeRange<'a>,
+    (&'a L, &'a R),
+    |pair| pair
+);
+slot_iter!(
+    RightRange,
+    Slab<L, R>,
+    TreeRange<'a>,
+    (&'a R, &'a L),
+    |pair| (pair.1, pair.0)
+);
+
+slot_iter!(
+    InversePairs<LS>,
+    Slab<R, L>,
+    Ids<'a, LS::Index>,
+    (&'a L, &'a R),
+    |pair| (pair.1, pair.0)
+);
+slot_iter!(
+    InversePairsByRight<RS>,
+    Slab<R, L>,
+    Ids<'a, RS::Index>,
+    (&'a L, &'a R),
+    |pair| (pair.1, pair.0)
+);
+
+borrowed_iter!(
+    MultiPairs,
+    Groups<'a, Pair<L, R, ByLeft>, Pair<L, R, ByRight>>,
+    &'a Pair<L, R, ByRight>,
+    (&'a L, &'a R),
+    |pair| (pair.left(), pair.right())
+);
+borrowed_iter!(
+    MultiPairsByRight,
+    Groups<'a, Pair<L, R, ByRight>, Pair<L, R, ByLeft>>,
+    &'a Pair<L, R, ByLeft>,
+    (&'a L, &'a R),
+    |pair| (pair.left(), pair.right())
+);
+borrowed_iter!(
+    MultiLeftRange,
+    GroupRange<'a, Pair<L, R, ByLeft>, Pair<L, R, ByRight>>,
+    &'a Pair<L, R, ByRight>,
+    (&'a L, &'a R),
+    |pair| (pair.left(), pair.right())
+);
+borrowed_iter!(
+    MultiRightRange,
+    GroupRange<'a, Pair<L, R, ByRight>, Pair<L, R, ByLeft>>,
+    &'a Pair<L, R, ByLeft>,
+    (&'a R, &'a L),
+    |pair| (pair.right(), pair.left())
+);
+borrowed_iter!(
+    IndexedPairsByRight,
+    btree_set::Iter<'a, Pair<L, R, ByRight>>,
+    &'a Pair<L, R, ByRight>,
+    (&'a L, &'a R),
+    |pair| (pair.left(), pair.right())
+);
+borrowed_iter!(
+    IndexedRightRange,
+    btree_set::Range<'a, Pair<L, R, ByRight>>,
+    &'a Pair<L, R, ByRight>,
+    (&'a R, &'a L),
+    |pair| (pair.right(), pair.left())
+);
+borrowed_iter!(
+    RightsByLeft,
+    btree_set::Iter<'a, Pair<L, R, ByRight>>,
+    &'a Pair<L, R, ByRight>,
+    &'a R,
+    |pair| pair.right()
+);
+borrowed_iter!(
+    LeftsByRight,
+    btree_set::Iter<'a, Pair<L, R, ByLeft>>,
+    &'a Pair<L, R, ByLeft>,
+    &'a L,
+    |pair| pair.left()
+);
+
+impl<'a, L, R, LS: Side, RS: Side> IntoIterator for &'a TwoWayMap<L, R, LS, RS> {
+    type Item = (&'a L, &'a R);
+
+    type IntoIter = Pairs<'a, L, R, LS>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pairs()
+    }
+}
+
+// Owns the slab and the index it walks, and moves each pair out of its slot as it goes.
+// The pairs it doesn't get to are dropped with the slab.
+pub struct IntoIter<L, R, LS: Side = Ordered> {
+    slab: Slab<L, R>,
+    index: LS::Index,
+    walk: Walk,
+    len: usize,
+}
+
+impl<L, R, LS: Side> IntoIter<L, R, LS> {
+    pub(crate) fn new<RS: Side>(map: TwoWayMap<L, R, LS, RS>) -> Self {
+        let walk = Walk::all(&map.left_to_right);
+        Self {
+            len: map.left_to_right.len(),
+            slab: map.slab,
+            index: map.left_to_right,
+            walk,
+        }
+    }
+}
+
+impl<L, R, LS: Side> Iterator for IntoIter<L, R, LS> {
+    type Item = (L, R);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.walk.next(&self.index)?;
+        Some(take(&mut self.slab, &mut self.len, id))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<L, R, LS: Side> DoubleEndedIterator for IntoIter<L, R, LS> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let id = self.walk.next_back(&self.index)?;
+        Some(take(&mut self.slab, &mut self.len, id))
+    }
+}
+
+impl<L, R, LS: Side> ExactSizeIterator for IntoIter<L, R, LS> {}
+
+impl<L, R, LS: Side> FusedIterator for IntoIter<L, R, LS> {}
+
+impl<L: Debug, R: Debug, LS: Side> Debug for IntoIter<L, R, LS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rest = Rest(&self.slab, &self.index, self.walk);
+        f.debug_tuple("IntoIter").field(&rest).finish()
+    }
+}
+
+impl<L, R, LS: Side, RS: Side> IntoIterator for TwoWayMap<L, R, LS, RS> {
+    type Item = (L, R);
+
+    type IntoIter = IntoIter<L, R, LS>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter::new(self)
+    }
+}
+
+// Like `IntoIter`, but yields the pairs in the order of the right index
+pub struct IntoIterByRight<L, R, RS: Side = Ordered> {
+    slab: Slab<L, R>,
+    index: RS::Index,
+    walk: Walk,
+    len: usize,
+}
+
+impl<L, R, RS: Side> IntoIterByRight<L, R, RS> {
+    pub(crate) fn new<LS: Side>(map: TwoWayMap<L, R, LS, RS>) -> Self {
+        let walk = Walk::all(&map.right_to_left);
+        Self {
+            len: map.right_to_left.len(),
+            slab: map.slab,
+            index: map.right_to_left,
+            walk,
+        }
+    }
+}
+
+impl<L, R, RS: Side> Iterator for IntoIterByRight<L, R, RS> {
+    type Item = (L, R);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.walk.next(&self.index)?;
+        Some(take(&mut self.slab, &mut self.len, id))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<L, R, RS: Side> DoubleEndedIterator for IntoIterByRight<L, R, RS> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let id = self.walk.next_back(&self.index)?;
+        Some(take(&mut self.slab, &mut self.len, id))
+    }
+}
+
+impl<L, R, RS: Side> ExactSizeIterator for IntoIterByRight<L, R, RS> {}
+
+impl<L, R, RS: Side> FusedIterator for IntoIterByRight<L, R, RS> {}
+
+impl<L: Debug, R: Debug, RS: Side> Debug for IntoIterByRight<L, R, RS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rest = Rest(&self.slab, &self.index, self.walk);
+        f.debug_tuple("IntoIterByRight").field(&rest).finish()
+    }
+}
+
+fn take<L, R>(slab: &mut Slab<L, R>, len: &mut usize, id: Id) -> (L, R) {
+    *len -= 1;
+    slab.remove(id)
+        .unwrap_or_else(|| panic!("two_way_map: {}", InvariantViolation::VacantSlot))
+}
+
+// The pairs an owning iterator has left, for `Debug`
+struct Rest<'a, L, R, I>(&'a Slab<L, R>, &'a I, Walk);
+
+impl<L: Debug, R: Debug, I: SideIndex> Debug for Rest<'_, L, R, I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Rest(slab, index, mut walk) = *self;
+        let pairs = iter::from_fn(|| walk.next(index).map(|id| slab.pair(id)));
+        f.debug_list().entries(pairs).finish()
+    }
+}
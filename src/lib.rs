@@ -1,4 +1,43 @@
+mod bi_multi_map;
+mod compose;
+mod cursor;
+mod entry;
+mod indexed_multi_map;
+mod invariants;
+mod inverse;
+mod iter;
 mod mem;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod set_ops;
+mod side;
+mod slab;
+mod table;
+mod tree;
 mod two_way_map;
+mod unwind;
+mod value_mut;
 
-pub use two_way_map::TwoWayMap;
+pub use bi_multi_map::BiMultiMap;
+pub use compose::{ComposedPairs, ComposedView, Join, Joined};
+pub use cursor::{LeftCursor, LeftCursorMut, RightCursor, RightCursorMut};
+pub use entry::{
+    LeftEntry, OccupiedLeftEntry, OccupiedRightEntry, RightEntry, VacantLeftEntry, VacantRightEntry,
+};
+pub use indexed_multi_map::IndexedMultiMap;
+pub use invariants::InvariantViolation;
+pub use inverse::InverseView;
+pub use iter::{
+    IndexedPairsByRight, IndexedRightRange, IntoIter, IntoIterByRight, InversePairs,
+    InversePairsByRight, LeftRange, LeftValues, LeftsByRight, MultiLeftRange, MultiPairs,
+    MultiPairsByRight, MultiRightRange, Pairs, PairsByRight, RefIter, RightRange, RightValues,
+    RightsByLeft,
+};
+#[cfg(feature = "serde")]
+pub use serde_impl::as_map;
+pub use set_ops::{ConflictPolicy, PairConflict, Resolution};
+pub use side::{Hashed, Ordered, Side, SideIndex, SideKey, SideLookup};
+pub use two_way_map::{
+    Conflict, ConflictError, HashTwoWayMap, Overwritten, ReplaceError, TwoWayMap,
+};
+pub use value_mut::{LeftMut, RightMut};
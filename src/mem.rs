@@ -1,27 +1,157 @@
 use bytemuck::TransparentWrapper;
 use std::{
     borrow::Borrow,
+    cmp::Ordering,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
     ops::{Bound, RangeBounds},
 };
 
-#[derive(Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
-pub struct Rc<T: ?Sized>(pub std::rc::Rc<T>);
+// A handle to a pair of a `BiMultiMap` or an `IndexedMultiMap`. Both values live in a single
+// allocation that is shared by the two indexes of the map, so a pair costs one allocation
+// instead of one per value. `F` tells which of the two values the handle is ordered, hashed
+// and looked up by. `TwoWayMap` keeps its pairs in a slab instead.
+//
+// Backed by `std::sync::Arc` rather than `std::rc::Rc` so that the map is `Send` and `Sync`
+// whenever `L` and `R` are. The reference count is only touched once per insert and once per
+// removal, so the atomic operations are not noticeable next to the tree operations.
+pub struct Pair<L, R, F> {
+    inner: std::sync::Arc<(L, R)>,
+    by: PhantomData<F>,
+}
+
+pub trait Field<L, R> {
+    type Key;
+
+    fn key<'a>(pair: (&'a L, &'a R)) -> &'a Self::Key;
+
+    // The same choice between the two value vectors of a slab
+    fn column<'a>(lefts: &'a [Option<L>], rights: &'a [Option<R>]) -> &'a [Option<Self::Key>];
+}
+
+#[derive(Debug)]
+pub struct ByLeft;
+
+#[derive(Debug)]
+pub struct ByRight;
+
+impl<L, R> Field<L, R> for ByLeft {
+    type Key = L;
+
+    fn key<'a>((left, _): (&'a L, &'a R)) -> &'a L {
+        left
+    }
+
+    fn column<'a>(lefts: &'a [Option<L>], _: &'a [Option<R>]) -> &'a [Option<L>] {
+        lefts
+    }
+}
+
+impl<L, R> Field<L, R> for ByRight {
+    type Key = R;
+
+    fn key<'a>((_, right): (&'a L, &'a R)) -> &'a R {
+        right
+    }
+
+    fn column<'a>(_: &'a [Option<L>], rights: &'a [Option<R>]) -> &'a [Option<R>] {
+        rights
+    }
+}
+
+impl<L, R, F> Pair<L, R, F> {
+    pub fn new(left: L, right: R) -> Self {
+        Self {
+            inner: std::sync::Arc::new((left, right)),
+            by: PhantomData,
+        }
+    }
+
+    // Another handle to the same allocation, keyed by `G`
+    pub fn share<G>(&self) -> Pair<L, R, G> {
+        Pair {
+            inner: self.inner.clone(),
+            by: PhantomData,
+        }
+    }
+
+    pub fn left(&self) -> &L {
+        &self.inner.0
+    }
+
+    pub fn right(&self) -> &R {
+        &self.inner.1
+    }
+
+    // Whether both handles point to the same allocation
+    pub fn same_pair<G>(&self, other: &Pair<L, R, G>) -> bool {
+        std::sync::Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    // Moves the values out of the last handle to a pair, once the indexes hold no other
+    pub fn into_values(this: Self) -> (L, R) {
+        std::sync::Arc::try_unwrap(this.inner)
+            .unwrap_or_else(|_| panic!("two_way_map: a removed pair still has other handles"))
+    }
+}
+
+impl<L, R, F: Field<L, R>> Pair<L, R, F> {
+    pub fn key(&self) -> &F::Key {
+        let (left, right) = &*self.inner;
+        F::key((left, right))
+    }
+}
+
+impl<L, R, F> Pair<L, R, F> {
+    // The value the handle would be keyed by if it was keyed by `G`
+    pub fn key_by<G: Field<L, R>>(&self) -> &G::Key {
+        let (left, right) = &*self.inner;
+        G::key((left, right))
+    }
+}
+
+impl<L: Debug, R: Debug, F> Debug for Pair<L, R, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl<L, R, F: Field<L, R>> PartialEq for Pair<L, R, F>
+where
+    F::Key: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl<L, R, F: Field<L, R>> Eq for Pair<L, R, F> where F::Key: Eq {}
 
-impl<T> Rc<T> {
-    pub fn new(val: T) -> Self {
-        Self(std::rc::Rc::new(val))
+impl<L, R, F: Field<L, R>> PartialOrd for Pair<L, R, F>
+where
+    F::Key: Ord,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
-impl<T> Clone for Rc<T> {
-    fn clone(&self) -> Self {
-        Self(self.0.clone())
+impl<L, R, F: Field<L, R>> Ord for Pair<L, R, F>
+where
+    F::Key: Ord,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(other.key())
     }
 }
 
-impl<T: ?Sized> AsRef<T> for Rc<T> {
-    fn as_ref(&self) -> &T {
-        &self.0
+impl<L, R, F: Field<L, R>> Hash for Pair<L, R, F>
+where
+    F::Key: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
     }
 }
 
@@ -54,8 +184,11 @@ pub fn wrap_bound<T: ?Sized>(bound: Bound<&T>) -> Bound<&Wrapper<T>> {
     bound.map(|v| wrap_ref(v))
 }
 
-impl<Q: ?Sized, T: Borrow<Q>> Borrow<Wrapper<Q>> for Rc<T> {
+impl<Q: ?Sized, L, R, F: Field<L, R>> Borrow<Wrapper<Q>> for Pair<L, R, F>
+where
+    F::Key: Borrow<Q>,
+{
     fn borrow(&self) -> &Wrapper<Q> {
-        Wrapper::wrap_ref(self.0.as_ref().borrow())
+        Wrapper::wrap_ref(self.key().borrow())
     }
 }
@@ -0,0 +1,147 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+
+use crate::side::{Side, SideKey};
+use crate::two_way_map::TwoWayMap;
+
+// A map is written as a sequence of `(left, right)` pairs in the order of `pairs()`.
+// Reading it back fails on the first pair whose left or right value was already seen
+// instead of silently dropping the earlier pair like `insert` does.
+impl<L, R, LS: Side, RS: Side> Serialize for TwoWayMap<L, R, LS, RS>
+where
+    L: Serialize,
+    R: Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for pair in self.pairs() {
+            seq.serialize_element(&pair)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de, L, R, LS: SideKey<L>, RS: SideKey<R>> Deserialize<'de> for TwoWayMap<L, R, LS, RS>
+where
+    L: Deserialize<'de>,
+    R: Deserialize<'de>,
+    Self: Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(PairsVisitor(PhantomData))
+    }
+}
+
+struct PairsVisitor<M>(PhantomData<M>);
+
+impl<'de, L, R, LS: SideKey<L>, RS: SideKey<R>> Visitor<'de>
+    for PairsVisitor<TwoWayMap<L, R, LS, RS>>
+where
+    L: Deserialize<'de>,
+    R: Deserialize<'de>,
+    TwoWayMap<L, R, LS, RS>: Default,
+{
+    type Value = TwoWayMap<L, R, LS, RS>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of pairs without repeated left or right values")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut map = TwoWayMap::default();
+        let mut index = 0;
+        while let Some((left, right)) = seq.next_element::<(L, R)>()? {
+            insert_unique(&mut map, left, right, index)?;
+            index += 1;
+        }
+        Ok(map)
+    }
+}
+
+fn insert_unique<L, R, LS: SideKey<L>, RS: SideKey<R>, E: de::Error>(
+    map: &mut TwoWayMap<L, R, LS, RS>,
+    left: L,
+    right: R,
+    index: usize,
+) -> Result<(), E> {
+    if map.contains_left(&left) {
+        return Err(E::custom(format_args!(
+            "left value of pair {index} is already mapped"
+        )));
+    }
+    if map.contains_right(&right) {
+        return Err(E::custom(format_args!(
+            "right value of pair {index} is already mapped"
+        )));
+    }
+    map.insert(left, right);
+    Ok(())
+}
+
+// Writes the map as a serde map from left to right values instead of a sequence of pairs,
+// which reads better in formats such as JSON when the left values are strings.
+// Use it with `#[serde(with = "two_way_map::as_map")]`.
+pub mod as_map {
+    use super::*;
+
+    pub fn serialize<L, R, LS, RS, S>(
+        map: &TwoWayMap<L, R, LS, RS>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        L: Serialize,
+        R: Serialize,
+        LS: Side,
+        RS: Side,
+        S: Serializer,
+    {
+        let mut out = serializer.serialize_map(Some(map.len()))?;
+        for (left, right) in map.pairs() {
+            out.serialize_entry(left, right)?;
+        }
+        out.end()
+    }
+
+    pub fn deserialize<'de, L, R, LS, RS, D>(
+        deserializer: D,
+    ) -> Result<TwoWayMap<L, R, LS, RS>, D::Error>
+    where
+        L: Deserialize<'de>,
+        R: Deserialize<'de>,
+        LS: SideKey<L>,
+        RS: SideKey<R>,
+        TwoWayMap<L, R, LS, RS>: Default,
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(EntriesVisitor(PhantomData))
+    }
+
+    struct EntriesVisitor<M>(PhantomData<M>);
+
+    impl<'de, L, R, LS: SideKey<L>, RS: SideKey<R>> Visitor<'de>
+        for EntriesVisitor<TwoWayMap<L, R, LS, RS>>
+    where
+        L: Deserialize<'de>,
+        R: Deserialize<'de>,
+        TwoWayMap<L, R, LS, RS>: Default,
+    {
+        type Value = TwoWayMap<L, R, LS, RS>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a map without repeated keys or values")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut entries: A) -> Result<Self::Value, A::Error> {
+            let mut map = TwoWayMap::default();
+            let mut index = 0;
+            while let Some((left, right)) = entries.next_entry::<L, R>()? {
+                insert_unique(&mut map, left, right, index)?;
+                index += 1;
+            }
+            Ok(map)
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use std::error::Error;
+use std::fmt::{self, Debug, Display};
+use std::iter;
+
+use crate::mem::Field;
+use crate::side::Ids;
+use crate::slab::{Id, Keys, NIL, Slab};
+use crate::tree::Tree;
+use crate::two_way_map::{Conflict, TwoWayMap};
+
+// Set operations treat a map as a set of pairs. A pair of one map can agree with a pair of
+// the other on one value but not on the other, so both can't be in the result: `union_with`
+// and `symmetric_difference` take a `ConflictPolicy` to decide which one stays. The
+// intersection and the difference only ever hold pairs of `self`, so they can't conflict.
+//
+// All of them walk the left indexes of both maps side by side, then the right ones, the way
+// two sorted lists are merged, and build the indexes of the result from the sorted runs
+// they produce instead of inserting pair by pair.
+
+// A pair of the other map that shares its left or right value with one or two pairs of
+// this map without being equal to them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PairConflict<L, R> {
+    pub other: (L, R),
+    // The pair of this map with the same left value
+    pub by_left: Option<(L, R)>,
+    // The pair of this map with the same right value
+    pub by_right: Option<(L, R)>,
+}
+
+impl<L, R> PairConflict<L, R> {
+    pub fn conflict(&self) -> Conflict {
+        match (&self.by_left, &self.by_right) {
+            (Some(_), Some(_)) => Conflict::Both,
+            (Some(_), None) => Conflict::Left,
+            (None, _) => Conflict::Right,
+        }
+    }
+}
+
+impl<L: Debug, R: Debug> Display for PairConflict<L, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "pair {:?} of the other map conflicts with", self.other)?;
+        match (&self.by_left, &self.by_right) {
+            (Some(by_left), Some(by_right)) => write!(f, " {by_left:?} and {by_right:?}"),
+            (Some(pair), None) | (None, Some(pair)) => write!(f, " {pair:?}"),
+            (None, None) => write!(f, " nothing"),
+        }
+    }
+}
+
+impl<L: Debug, R: Debug> Error for PairConflict<L, R> {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    KeepSelf,
+    KeepOther,
+}
+
+// What to do with a pair of the other map that conflicts with pairs of this one. Keeping
+// the pair of the other map drops every pair of this map it conflicts with.
+pub enum ConflictPolicy<'a, L, R> {
+    KeepSelf,
+    KeepOther,
+    // Fail with the first conflict, in the left order of the other map
+    Error,
+    // Called once per conflicting pair of the other map, in its left order
+    Custom(&'a mut dyn FnMut(PairConflict<&L, &R>) -> Resolution),
+}
+
+impl<L, R> Debug for ConflictPolicy<'_, L, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictPolicy::KeepSelf => write!(f, "KeepSelf"),
+            ConflictPolicy::KeepOther => write!(f, "KeepOther"),
+            ConflictPolicy::Error => write!(f, "Error"),
+            ConflictPolicy::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+impl<L: Ord + Clone, R: Ord + Clone> TwoWayMap<L, R> {
+    // The pairs of both maps, each pair that's in both only once
+    pub fn union_with(
+        &self,
+        other: &Self,
+        policy: ConflictPolicy<'_, L, R>,
+    ) -> Result<Self, PairConflict<L, R>> {
+        let dropped = self.resolve(other, policy)?;
+        let keep = Selection {
+            mine: true,
+            theirs: true,
+            shared: true,
+        };
+        Ok(self.combine(other, keep, &dropped))
+    }
+
+    // The pairs that are in both maps
+    pub fn intersection(&self, other: &Self) -> Self {
+        let keep = Selection {
+            mine: false,
+            theirs: false,
+            shared: true,
+        };
+        self.combine(other, keep, &[])
+    }
+
+    // The pairs of this map that aren't in the other one, including those that conflict
+    // with a pair of the other one
+    pub fn difference(&self, other: &Self) -> Self {
+        let keep = Selection {
+            mine: true,
+            theirs: false,
+            shared: false,
+        };
+        self.combine(other, keep, &[])
+    }
+
+    // The pairs that are in exactly one of the maps
+    pub fn symmetric_difference(
+        &self,
+        other: &Self,
+        policy: ConflictPolicy<'_, L, R>,
+    ) -> Result<Self, PairConflict<L, R>> {
+        let dropped = self.resolve(other, policy)?;
+        let keep = Selection {
+            mine: true,
+            theirs: true,
+            shared: false,
+        };
+        Ok(self.combine(other, keep, &dropped))
+    }
+
+    // Finds the conflicts and returns the slots of the pairs, of either map, that lose them,
+    // sorted
+    fn resolve(
+        &self,
+        other: &Self,
+        mut policy: ConflictPolicy<'_, L, R>,
+    ) -> Result<Vec<Slot>, PairConflict<L, R>> {
+        let (mine, theirs) = (&self.slab, &other.slab);
+        // In the left order of the other map, since that's how the left indexes are walked
+        let mut clashes: Vec<Clash<'_, L, R>> = self
+            .merge_by_left(other)
+            .filter_map(|step| match step {
+                Step::Both(m, t) if mine.right(m) != theirs.right(t) => Some(Clash {
+                    theirs: Source::of(theirs, Owner::Theirs, t),
+                    by_left: Some(Source::of(mine, Owner::Mine, m)),
+                    by_right: None,
+                }),
+                _ => None,
+            })
+            .collect();
+
+        let mut right_only = Vec::new();
+        for step in self.merge_by_right(other) {
+            let Step::Both(m, t) = step else {
+                continue;
+            };
+            let theirs_left = theirs.left(t);
+            if mine.left(m) == theirs_left {
+                continue;
+            }
+            match clashes.binary_search_by(|clash| clash.theirs.left.cmp(theirs_left)) {
+                Ok(index) => clashes[index].by_right = Some(Source::of(mine, Owner::Mine, m)),
+                Err(_) => right_only.push(Clash {
+                    theirs: Source::of(theirs, Owner::Theirs, t),
+                    by_left: None,
+                    by_right: Some(Source::of(mine, Owner::Mine, m)),
+                }),
+            }
+        }
+        clashes.append(&mut right_only);
+        clashes.sort_by(|a, b| a.theirs.left.cmp(b.theirs.left));
+
+        let mut dropped = Vec::new();
+        for clash in &clashes {
+            let resolution = match &mut policy {
+                ConflictPolicy::KeepSelf => Resolution::KeepSelf,
+                ConflictPolicy::KeepOther => Resolution::KeepOther,
+                ConflictPolicy::Error => return Err(clash.to_owned()),
+                ConflictPolicy::Custom(f) => f(clash.borrowed()),
+            };
+            match resolution {
+                Resolution::KeepSelf => dropped.push(clash.theirs.slot),
+                Resolution::KeepOther => {
+                    dropped.extend(clash.by_left.iter().chain(&clash.by_right).map(|p| p.slot))
+                }
+            }
+        }
+        dropped.sort_unstable();
+        Ok(dropped)
+    }
+
+    // Builds a map from the pairs `keep` selects that aren't `dropped`. The left walk
+    // copies them into a new slab in the order of their left values, remembering where
+    // each one went, and the right walk picks the same pairs, so it only has to look up
+    // their new slots. Neither index compares anything while it's built.
+    fn combine(&self, other: &Self, keep: Selection, dropped: &[Slot]) -> Self {
+        let slabs = [&self.slab, &other.slab];
+        let mut moved = [vec![NIL; self.slab.ids()], vec![NIL; other.slab.ids()]];
+        let mut slab = Slab::new();
+        let mut by_left = Vec::new();
+        for step in self.merge_by_left(other) {
+            for (owner, id) in keep.pick(step, slabs, dropped).into_iter().flatten() {
+                let (left, right) = slabs[owner as usize].pair(id);
+                let new = slab.insert(left.clone(), right.clone());
+                moved[owner as usize][id as usize] = new;
+                by_left.push(new);
+            }
+        }
+
+        let mut by_right = Vec::with_capacity(by_left.len());
+        for step in self.merge_by_right(other) {
+            for (owner, id) in keep.pick(step, slabs, dropped).into_iter().flatten() {
+                by_right.push(moved[owner as usize][id as usize]);
+            }
+        }
+
+        let map = TwoWayMap {
+            slab,
+            left_to_right: Tree::from_sorted(by_left),
+            right_to_left: Tree::from_sorted(by_right),
+        };
+        map.debug_check();
+        map
+    }
+
+    fn merge_by_left<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = Step> + 'a {
+        let mine = (&self.left_to_right, self.slab.by_left());
+        merge(mine, (&other.left_to_right, other.slab.by_left()))
+    }
+
+    fn merge_by_right<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = Step> + 'a {
+        let mine = (&self.right_to_left, self.slab.by_right());
+        merge(mine, (&other.right_to_left, other.slab.by_right()))
+    }
+}
+
+// Which map a slot belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Owner {
+    Mine,
+    Theirs,
+}
+
+// A slot of one of the maps, which tells its pair apart from an equal pair of the other
+type Slot = (Owner, Id);
+
+// Which pairs end up in the result: those only in this map, those only in the other one
+// and those in both. A pair that conflicts counts as only in its own map.
+struct Selection {
+    mine: bool,
+    theirs: bool,
+    shared: bool,
+}
+
+impl Selection {
+    fn pick<L: Eq, R: Eq>(
+        &self,
+        step: Step,
+        [mine, theirs]: [&Slab<L, R>; 2],
+        dropped: &[Slot],
+    ) -> [Option<Slot>; 2] {
+        let kept = |slot: Slot| dropped.binary_search(&slot).is_err();
+        let (m, t) = match step {
+            Step::Mine(m) => (Some(m), None),
+            Step::Theirs(t) => (None, Some(t)),
+            Step::Both(m, t) if mine.pair(m) == theirs.pair(t) => {
+                return [self.shared.then_some((Owner::Mine, m)), None];
+            }
+            Step::Both(m, t) => (Some(m), Some(t)),
+        };
+        [
+            m.map(|m| (Owner::Mine, m))
+                .filter(|&slot| self.mine && kept(slot)),
+            t.map(|t| (Owner::Theirs, t))
+                .filter(|&slot| self.theirs && kept(slot)),
+        ]
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Step {
+    Mine(Id),
+    Theirs(Id),
+    Both(Id, Id),
+}
+
+// Walks two trees in their common order, pairing up the slots with equal keys
+fn merge<'a, L, R, F>(
+    (mine, mine_keys): (&'a Tree, Keys<'a, L, R, F>),
+    (theirs, theirs_keys): (&'a Tree, Keys<'a, L, R, F>),
+) -> impl Iterator<Item = Step> + 'a
+where
+    F: Field<L, R> + 'a,
+    F::Key: Ord,
+{
+    let mut mine = Ids::new(mine).peekable();
+    let mut theirs = Ids::new(theirs).peekable();
+    iter::from_fn(move || match (mine.peek(), theirs.peek()) {
+        (None, None) => None,
+        (Some(_), None) => mine.next().map(Step::Mine),
+        (None, Some(_)) => theirs.next().map(Step::Theirs),
+        (Some(&a), Some(&b)) => match mine_keys.get(a).cmp(theirs_keys.get(b)) {
+            std::cmp::Ordering::Less => mine.next().map(Step::Mine),
+            std::cmp::Ordering::Greater => theirs.next().map(Step::Theirs),
+            std::cmp::Ordering::Equal => Some(Step::Both(mine.next()?, theirs.next()?)),
+        },
+    })
+}
+
+// A pair of one of the maps, together with the slot that tells it apart
+struct Source<'a, L, R> {
+    left: &'a L,
+    right: &'a R,
+    slot: Slot,
+}
+
+impl<'a, L, R> Source<'a, L, R> {
+    fn of(slab: &'a Slab<L, R>, owner: Owner, id: Id) -> Self {
+        let (left, right) = slab.pair(id);
+        Source {
+            left,
+            right,
+            slot: (owner, id),
+        }
+    }
+
+    fn values(&self) -> (&'a L, &'a R) {
+        (self.left, self.right)
+    }
+}
+struct Clash<'a, L, R> {
+    theirs: Source<'a, L, R>,
+    by_left: Option<Source<'a, L, R>>,
+    by_right: Option<Source<'a, L, R>>,
+}
+
+impl<'a, L: Clone, R: Clone> Clash<'a, L, R> {
+    fn borrowed(&self) -> PairConflict<&'a L, &'a R> {
+        PairConflict {
+            other: self.theirs.values(),
+            by_left: self.by_left.as_ref().map(Source::values),
+            by_right: self.by_right.as_ref().map(Source::values),
+        }
+    }
+
+    fn to_owned(&self) -> PairConflict<L, R> {
+        let owned = |(left, right): (&L, &R)| (left.clone(), right.clone());
+        let conflict = self.borrowed();
+        PairConflict {
+            other: owned(conflict.other),
+            by_left: conflict.by_left.map(owned),
+            by_right: conflict.by_right.map(owned),
+        }
+    }
+}
@@ -0,0 +1,328 @@
+use std::{
+    borrow::Borrow,
+    hash::{BuildHasher, Hash, RandomState},
+    iter::FusedIterator,
+    marker::PhantomData,
+};
+
+use crate::mem::Field;
+use crate::slab::{Id, Keys, NIL};
+use crate::table::Table;
+use crate::tree::Tree;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+// Strategy used for the index of one side of the map: `Ordered` keeps a balanced tree,
+// `Hashed` keeps a hash table built with the hasher `H`. Both hold the ids of the slots
+// the pairs live in. Each side picks its own, so range queries are only available on the
+// sides that are `Ordered`.
+pub trait Side: sealed::Sealed {
+    type Index: SideIndex;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ordered;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Hashed<H = RandomState>(PhantomData<H>);
+
+impl sealed::Sealed for Ordered {}
+impl<H> sealed::Sealed for Hashed<H> {}
+
+impl Side for Ordered {
+    type Index = Tree;
+}
+
+impl<H: Clone> Side for Hashed<H> {
+    type Index = Table<H>;
+}
+
+// Operations that don't look at the values. Iterating an index goes from slot to slot:
+// in the order of the values for a tree, in the order of the ids for a table.
+pub trait SideIndex: Clone {
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    fn clear(&mut self);
+    // Takes a slot out of the index, which only follows links
+    fn remove(&mut self, id: Id);
+    // An empty index that hashes the same way as `self`
+    fn empty_like(&self) -> Self;
+    fn first(&self) -> Option<Id>;
+    fn last(&self) -> Option<Id>;
+    fn next(&self, id: Id) -> Option<Id>;
+    fn prev(&self, id: Id) -> Option<Id>;
+}
+
+impl SideIndex for Tree {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn clear(&mut self) {
+        self.clear();
+    }
+
+    fn remove(&mut self, id: Id) {
+        self.remove(id);
+    }
+
+    fn empty_like(&self) -> Self {
+        Tree::new()
+    }
+
+    fn first(&self) -> Option<Id> {
+        self.first()
+    }
+
+    fn last(&self) -> Option<Id> {
+        self.last()
+    }
+
+    fn next(&self, id: Id) -> Option<Id> {
+        self.next(id)
+    }
+
+    fn prev(&self, id: Id) -> Option<Id> {
+        self.prev(id)
+    }
+}
+
+impl<H: Clone> SideIndex for Table<H> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn clear(&mut self) {
+        self.clear();
+    }
+
+    fn remove(&mut self, id: Id) {
+        self.remove(id);
+    }
+
+    fn empty_like(&self) -> Self {
+        Table::with_hasher(self.hasher().clone())
+    }
+
+    fn first(&self) -> Option<Id> {
+        self.first()
+    }
+
+    fn last(&self) -> Option<Id> {
+        self.last()
+    }
+
+    fn next(&self, id: Id) -> Option<Id> {
+        self.next(id)
+    }
+
+    fn prev(&self, id: Id) -> Option<Id> {
+        self.prev(id)
+    }
+}
+
+// A side that can index pairs by values of type `T`
+pub trait SideKey<T>: Side + SideLookup<T, T> {
+    // Adds the slot `id` by its key in `keys`, unless a slot with an equal key is already
+    // in the index
+    fn insert<L, R, F>(index: &mut Self::Index, keys: Keys<'_, L, R, F>, id: Id) -> bool
+    where
+        F: Field<L, R, Key = T>;
+}
+
+// A side indexing pairs by `T` that can be queried with a borrowed `Q`, the same way
+// `BTreeSet::get` and `HashSet::get` can
+pub trait SideLookup<T, Q: ?Sized>: Side {
+    fn find<L, R, F>(index: &Self::Index, keys: Keys<'_, L, R, F>, key: &Q) -> Option<Id>
+    where
+        F: Field<L, R, Key = T>;
+
+    fn contains<L, R, F>(index: &Self::Index, keys: Keys<'_, L, R, F>, key: &Q) -> bool
+    where
+        F: Field<L, R, Key = T>,
+    {
+        Self::find(index, keys, key).is_some()
+    }
+}
+
+impl<T: Ord> SideKey<T> for Ordered {
+    fn insert<L, R, F>(index: &mut Tree, keys: Keys<'_, L, R, F>, id: Id) -> bool
+    where
+        F: Field<L, R, Key = T>,
+    {
+        index.insert(keys, id)
+    }
+}
+
+impl<T, Q> SideLookup<T, Q> for Ordered
+where
+    T: Ord + Borrow<Q>,
+    Q: Ord + ?Sized,
+{
+    fn find<L, R, F>(index: &Tree, keys: Keys<'_, L, R, F>, key: &Q) -> Option<Id>
+    where
+        F: Field<L, R, Key = T>,
+    {
+        index.find(keys, key)
+    }
+}
+
+impl<T: Hash + Eq, H: BuildHasher + Clone> SideKey<T> for Hashed<H> {
+    fn insert<L, R, F>(index: &mut Table<H>, keys: Keys<'_, L, R, F>, id: Id) -> bool
+    where
+        F: Field<L, R, Key = T>,
+    {
+        index.insert(keys, id)
+    }
+}
+
+impl<T, Q, H> SideLookup<T, Q> for Hashed<H>
+where
+    T: Hash + Eq + Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+    H: BuildHasher + Clone,
+{
+    fn find<L, R, F>(index: &Table<H>, keys: Keys<'_, L, R, F>, key: &Q) -> Option<Id>
+    where
+        F: Field<L, R, Key = T>,
+    {
+        index.find(keys, key)
+    }
+}
+
+// The slots an iteration over an index has left, from `front` to `back` inclusive. It
+// holds no reference, so the same walk serves iterators that borrow the index and those
+// that own it.
+#[derive(Clone, Copy, Debug)]
+pub struct Walk {
+    front: Id,
+    back: Id,
+}
+
+impl Walk {
+    pub(crate) const EMPTY: Walk = Walk {
+        front: NIL,
+        back: NIL,
+    };
+
+    pub(crate) fn between(front: Id, back: Id) -> Self {
+        Walk { front, back }
+    }
+
+    pub(crate) fn all<I: SideIndex>(index: &I) -> Self {
+        match (index.first(), index.last()) {
+            (Some(front), Some(back)) => Walk::between(front, back),
+            _ => Walk::EMPTY,
+        }
+    }
+
+    pub(crate) fn next<I: SideIndex>(&mut self, index: &I) -> Option<Id> {
+        let id = self.front;
+        if id == NIL {
+            return None;
+        }
+        match (id != self.back).then(|| index.next(id)).flatten() {
+            Some(next) => self.front = next,
+            None => *self = Walk::EMPTY,
+        }
+        Some(id)
+    }
+
+    pub(crate) fn next_back<I: SideIndex>(&mut self, index: &I) -> Option<Id> {
+        let id = self.back;
+        if id == NIL {
+            return None;
+        }
+        match (id != self.front).then(|| index.prev(id)).flatten() {
+            Some(prev) => self.back = prev,
+            None => *self = Walk::EMPTY,
+        }
+        Some(id)
+    }
+}
+
+// The ids of all slots in an index, in its order
+pub struct Ids<'a, I> {
+    index: &'a I,
+    walk: Walk,
+    len: usize,
+}
+
+// The ids of the slots in a range of a tree
+#[derive(Clone)]
+pub struct TreeRange<'a> {
+    tree: &'a Tree,
+    walk: Walk,
+}
+
+impl<'a, I: SideIndex> Ids<'a, I> {
+    pub(crate) fn new(index: &'a I) -> Self {
+        Ids {
+            index,
+            walk: Walk::all(index),
+            len: index.len(),
+        }
+    }
+}
+
+impl<I: SideIndex> Iterator for Ids<'_, I> {
+    type Item = Id;
+
+    fn next(&mut self) -> Option<Id> {
+        let id = self.walk.next(self.index)?;
+        self.len -= 1;
+        Some(id)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<I: SideIndex> DoubleEndedIterator for Ids<'_, I> {
+    fn next_back(&mut self) -> Option<Id> {
+        let id = self.walk.next_back(self.index)?;
+        self.len -= 1;
+        Some(id)
+    }
+}
+
+impl<I: SideIndex> ExactSizeIterator for Ids<'_, I> {}
+
+impl<I: SideIndex> FusedIterator for Ids<'_, I> {}
+
+impl<I> Clone for Ids<'_, I> {
+    fn clone(&self) -> Self {
+        Ids {
+            index: self.index,
+            walk: self.walk,
+            len: self.len,
+        }
+    }
+}
+
+impl<'a> TreeRange<'a> {
+    pub(crate) fn new(tree: &'a Tree, walk: Walk) -> Self {
+        TreeRange { tree, walk }
+    }
+}
+
+impl Iterator for TreeRange<'_> {
+    type Item = Id;
+
+    fn next(&mut self) -> Option<Id> {
+        self.walk.next(self.tree)
+    }
+}
+
+impl DoubleEndedIterator for TreeRange<'_> {
+    fn next_back(&mut self) -> Option<Id> {
+        self.walk.next_back(self.tree)
+    }
+}
+
+impl FusedIterator for TreeRange<'_> {}
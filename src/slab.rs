@@ -0,0 +1,155 @@
+use std::marker::PhantomData;
+
+use crate::invariants::InvariantViolation;
+use crate::mem::{ByLeft, ByRight, Field};
+
+// The pairs of a `TwoWayMap` live in the slots of a slab, and both indexes hold slot ids
+// instead of handles. The two values of a slot sit at the same position of two parallel
+// vectors, so swapping the vectors swaps the values of every pair without moving any.
+// Freed slots are reused first, so a map that keeps a steady size stops allocating once
+// the vectors have grown.
+pub type Id = u32;
+
+// The id no slot has, marking the end of a chain or a missing link
+pub(crate) const NIL: Id = Id::MAX;
+
+#[derive(Clone)]
+pub struct Slab<L, R> {
+    lefts: Vec<Option<L>>,
+    rights: Vec<Option<R>>,
+    // The free slots, the most recently freed last
+    free: Vec<Id>,
+}
+
+// The values of one side of the pairs in a slab, which is what an index is keyed by
+pub struct Keys<'a, L, R, F> {
+    slab: &'a Slab<L, R>,
+    by: PhantomData<F>,
+}
+
+impl<L, R> Slab<L, R> {
+    pub(crate) fn new() -> Self {
+        Slab {
+            lefts: Vec::new(),
+            rights: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.lefts.len() - self.free.len()
+    }
+
+    // One more than the highest id in use so far
+    pub(crate) fn ids(&self) -> usize {
+        self.lefts.len()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.lefts.clear();
+        self.rights.clear();
+        self.free.clear();
+    }
+
+    pub(crate) fn insert(&mut self, left: L, right: R) -> Id {
+        if let Some(id) = self.free.pop() {
+            self.lefts[id as usize] = Some(left);
+            self.rights[id as usize] = Some(right);
+            return id;
+        }
+
+        let id = Id::try_from(self.lefts.len())
+            .ok()
+            .filter(|&id| id != NIL)
+            .expect("two_way_map: too many pairs");
+        self.lefts.push(Some(left));
+        self.rights.push(Some(right));
+        id
+    }
+
+    pub(crate) fn remove(&mut self, id: Id) -> Option<(L, R)> {
+        let left = self.lefts.get_mut(id as usize)?.take()?;
+        let right = self.rights[id as usize].take().unwrap();
+        self.free.push(id);
+        Some((left, right))
+    }
+
+    pub(crate) fn get(&self, id: Id) -> Option<(&L, &R)> {
+        let left = self.lefts.get(id as usize)?.as_ref()?;
+        let right = self.rights[id as usize].as_ref().unwrap();
+        Some((left, right))
+    }
+
+    // The pair of a slot an index points to, which has to be there
+    pub(crate) fn pair(&self, id: Id) -> (&L, &R) {
+        self.get(id).unwrap_or_else(|| vacant())
+    }
+
+    pub(crate) fn left(&self, id: Id) -> &L {
+        self.pair(id).0
+    }
+
+    pub(crate) fn right(&self, id: Id) -> &R {
+        self.pair(id).1
+    }
+
+    pub(crate) fn left_mut(&mut self, id: Id) -> &mut L {
+        let slot = self.lefts.get_mut(id as usize).and_then(Option::as_mut);
+        slot.unwrap_or_else(|| vacant())
+    }
+
+    pub(crate) fn right_mut(&mut self, id: Id) -> &mut R {
+        let slot = self.rights.get_mut(id as usize).and_then(Option::as_mut);
+        slot.unwrap_or_else(|| vacant())
+    }
+
+    pub(crate) fn by_left(&self) -> Keys<'_, L, R, ByLeft> {
+        Keys {
+            slab: self,
+            by: PhantomData,
+        }
+    }
+
+    pub(crate) fn by_right(&self) -> Keys<'_, L, R, ByRight> {
+        Keys {
+            slab: self,
+            by: PhantomData,
+        }
+    }
+
+    // The same slots with the values of each pair swapped, so the ids stay valid
+    pub(crate) fn inverse(self) -> Slab<R, L> {
+        Slab {
+            lefts: self.rights,
+            rights: self.lefts,
+            free: self.free,
+        }
+    }
+}
+
+fn vacant() -> ! {
+    panic!("two_way_map: {}", InvariantViolation::VacantSlot)
+}
+
+impl<L, R> Default for Slab<L, R> {
+    fn default() -> Self {
+        Slab::new()
+    }
+}
+
+impl<'a, L, R, F: Field<L, R>> Keys<'a, L, R, F> {
+    pub(crate) fn get(self, id: Id) -> &'a F::Key {
+        // Only the vector of the side the index is keyed by is read
+        let column = F::column(&self.slab.lefts, &self.slab.rights);
+        let slot = column.get(id as usize).and_then(Option::as_ref);
+        slot.unwrap_or_else(|| vacant())
+    }
+}
+
+impl<L, R, F> Clone for Keys<'_, L, R, F> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<L, R, F> Copy for Keys<'_, L, R, F> {}
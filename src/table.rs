@@ -0,0 +1,187 @@
+use std::borrow::Borrow;
+use std::hash::{BuildHasher, Hash};
+
+use crate::mem::Field;
+use crate::slab::{Id, Keys, NIL};
+
+// The index of a `Hashed` side: a chained hash table of slot ids. The link of each slot
+// lives at its id in `links`, together with the hash it went in with, so growing the table
+// and taking a slot out never hash anything again. `Hash` and `Eq` are only called to find
+// a value, before anything is moved, which is why a panic in them leaves the table as it
+// was. Iterating goes through the ids in order.
+#[derive(Clone, Debug)]
+pub struct Table<H> {
+    hasher: H,
+    // The first slot of each bucket. The number of buckets is a power of two.
+    buckets: Vec<Id>,
+    links: Vec<Link>,
+    len: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Link {
+    next: Id,
+    hash: u64,
+}
+
+// The link of a slot that isn't in the table
+const OUT: Link = Link {
+    next: NIL - 1,
+    hash: 0,
+};
+
+impl<H> Table<H> {
+    pub(crate) fn with_hasher(hasher: H) -> Self {
+        Table {
+            hasher,
+            buckets: Vec::new(),
+            links: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub(crate) fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.buckets.fill(NIL);
+        self.links.clear();
+        self.len = 0;
+    }
+
+    fn bucket(&self, hash: u64) -> usize {
+        hash as usize & (self.buckets.len() - 1)
+    }
+
+    fn holds(&self, id: usize) -> bool {
+        self.links[id].next != OUT.next
+    }
+
+    pub(crate) fn first(&self) -> Option<Id> {
+        self.next_from(0)
+    }
+
+    pub(crate) fn last(&self) -> Option<Id> {
+        self.prev_from(self.links.len())
+    }
+
+    pub(crate) fn next(&self, id: Id) -> Option<Id> {
+        self.next_from(id as usize + 1)
+    }
+
+    pub(crate) fn prev(&self, id: Id) -> Option<Id> {
+        self.prev_from(id as usize)
+    }
+
+    fn next_from(&self, start: usize) -> Option<Id> {
+        let id = (start..self.links.len()).find(|&id| self.holds(id))?;
+        Some(id as Id)
+    }
+
+    fn prev_from(&self, end: usize) -> Option<Id> {
+        let id = (0..end).rev().find(|&id| self.holds(id))?;
+        Some(id as Id)
+    }
+
+    pub(crate) fn remove(&mut self, id: Id) {
+        let Link { next, hash } = self.links[id as usize];
+        let bucket = self.bucket(hash);
+        if self.buckets[bucket] == id {
+            self.buckets[bucket] = next;
+        } else {
+            let mut prev = self.buckets[bucket];
+            while self.links[prev as usize].next != id {
+                prev = self.links[prev as usize].next;
+            }
+            self.links[prev as usize].next = next;
+        }
+        self.links[id as usize] = OUT;
+        self.len -= 1;
+    }
+
+    // Doubles the number of buckets and moves every slot to its bucket there
+    fn grow(&mut self) {
+        let buckets = (self.buckets.len() * 2).max(8);
+        let old = std::mem::replace(&mut self.buckets, vec![NIL; buckets]);
+        for mut id in old {
+            while id != NIL {
+                let Link { next, hash } = self.links[id as usize];
+                let bucket = self.bucket(hash);
+                self.links[id as usize].next = self.buckets[bucket];
+                self.buckets[bucket] = id;
+                id = next;
+            }
+        }
+    }
+}
+
+impl<H: BuildHasher> Table<H> {
+    pub(crate) fn find<L, R, F, Q>(&self, keys: Keys<'_, L, R, F>, key: &Q) -> Option<Id>
+    where
+        F: Field<L, R>,
+        F::Key: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if self.len == 0 {
+            return None;
+        }
+        self.find_hashed(keys, key, self.hasher.hash_one(key))
+    }
+
+    fn find_hashed<L, R, F, Q>(&self, keys: Keys<'_, L, R, F>, key: &Q, hash: u64) -> Option<Id>
+    where
+        F: Field<L, R>,
+        F::Key: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let mut id = self.buckets[self.bucket(hash)];
+        while id != NIL {
+            let link = self.links[id as usize];
+            if link.hash == hash && keys.get(id).borrow() == key {
+                return Some(id);
+            }
+            id = link.next;
+        }
+        None
+    }
+
+    // Adds `id` unless a slot with an equal key is already in the table
+    pub(crate) fn insert<L, R, F>(&mut self, keys: Keys<'_, L, R, F>, id: Id) -> bool
+    where
+        F: Field<L, R>,
+        F::Key: Hash + Eq,
+    {
+        let key = keys.get(id);
+        let hash = self.hasher.hash_one(key);
+        if self.len > 0 && self.find_hashed(keys, key, hash).is_some() {
+            return false;
+        }
+
+        // Nothing is hashed or compared from here on
+        if self.len >= self.buckets.len() {
+            self.grow();
+        }
+        if self.links.len() <= id as usize {
+            self.links.resize(id as usize + 1, OUT);
+        }
+        let bucket = self.bucket(hash);
+        self.links[id as usize] = Link {
+            next: self.buckets[bucket],
+            hash,
+        };
+        self.buckets[bucket] = id;
+        self.len += 1;
+        true
+    }
+}
+
+impl<H: Default> Default for Table<H> {
+    fn default() -> Self {
+        Table::with_hasher(H::default())
+    }
+}
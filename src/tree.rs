@@ -0,0 +1,359 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::ops::Bound::{self, Excluded, Included, Unbounded};
+
+use crate::mem::Field;
+use crate::side::Walk;
+use crate::slab::{Id, Keys, NIL};
+
+// The index of an `Ordered` side: a treap of slot ids whose links live at each id in
+// `nodes`. The priority that keeps it balanced is derived from the id rather than from the
+// value, so taking a slot out or rotating it into place only follows links. Values are
+// only compared to find where a slot goes, before anything is moved, which is why a
+// panicking `Ord` leaves the tree as it was.
+#[derive(Clone, Debug)]
+pub struct Tree {
+    root: Id,
+    nodes: Vec<Node>,
+    len: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    parent: Id,
+    left: Id,
+    right: Id,
+}
+
+const UNLINKED: Node = Node {
+    parent: NIL,
+    left: NIL,
+    right: NIL,
+};
+
+// A fixed shuffle of the ids, the finalizer of SplitMix64. It's a bijection, so no two
+// slots get the same priority.
+fn priority(id: Id) -> u64 {
+    let mut z = u64::from(id).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn some(id: Id) -> Option<Id> {
+    (id != NIL).then_some(id)
+}
+
+impl Tree {
+    pub(crate) fn new() -> Self {
+        Tree {
+            root: NIL,
+            nodes: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.root = NIL;
+        self.nodes.clear();
+        self.len = 0;
+    }
+
+    fn node(&self, id: Id) -> Node {
+        self.nodes[id as usize]
+    }
+
+    fn node_mut(&mut self, id: Id) -> &mut Node {
+        &mut self.nodes[id as usize]
+    }
+
+    pub(crate) fn first(&self) -> Option<Id> {
+        self.leftmost(self.root)
+    }
+
+    pub(crate) fn last(&self) -> Option<Id> {
+        self.rightmost(self.root)
+    }
+
+    fn leftmost(&self, mut id: Id) -> Option<Id> {
+        while id != NIL && self.node(id).left != NIL {
+            id = self.node(id).left;
+        }
+        some(id)
+    }
+
+    fn rightmost(&self, mut id: Id) -> Option<Id> {
+        while id != NIL && self.node(id).right != NIL {
+            id = self.node(id).right;
+        }
+        some(id)
+    }
+
+    pub(crate) fn next(&self, id: Id) -> Option<Id> {
+        let node = self.node(id);
+        if node.right != NIL {
+            return self.leftmost(node.right);
+        }
+        let (mut child, mut parent) = (id, node.parent);
+        while parent != NIL && self.node(parent).right == child {
+            (child, parent) = (parent, self.node(parent).parent);
+        }
+        some(parent)
+    }
+
+    pub(crate) fn prev(&self, id: Id) -> Option<Id> {
+        let node = self.node(id);
+        if node.left != NIL {
+            return self.rightmost(node.left);
+        }
+        let (mut child, mut parent) = (id, node.parent);
+        while parent != NIL && self.node(parent).left == child {
+            (child, parent) = (parent, self.node(parent).parent);
+        }
+        some(parent)
+    }
+
+    pub(crate) fn find<L, R, F, Q>(&self, keys: Keys<'_, L, R, F>, key: &Q) -> Option<Id>
+    where
+        F: Field<L, R>,
+        F::Key: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut id = self.root;
+        while id != NIL {
+            id = match key.cmp(keys.get(id).borrow()) {
+                Ordering::Less => self.node(id).left,
+                Ordering::Greater => self.node(id).right,
+                Ordering::Equal => return Some(id),
+            };
+        }
+        None
+    }
+
+    // The first slot whose key is within `bound` taken as the start of a range
+    pub(crate) fn lower_bound<L, R, F, Q>(
+        &self,
+        keys: Keys<'_, L, R, F>,
+        bound: Bound<&Q>,
+    ) -> Option<Id>
+    where
+        F: Field<L, R>,
+        F::Key: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (mut id, mut found) = (self.root, NIL);
+        while id != NIL {
+            let key = keys.get(id).borrow();
+            let within = match bound {
+                Included(start) => key >= start,
+                Excluded(start) => key > start,
+                Unbounded => true,
+            };
+            if within {
+                found = id;
+                id = self.node(id).left;
+            } else {
+                id = self.node(id).right;
+            }
+        }
+        some(found)
+    }
+
+    // The last slot whose key is within `bound` taken as the end of a range
+    pub(crate) fn upper_bound<L, R, F, Q>(
+        &self,
+        keys: Keys<'_, L, R, F>,
+        bound: Bound<&Q>,
+    ) -> Option<Id>
+    where
+        F: Field<L, R>,
+        F::Key: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (mut id, mut found) = (self.root, NIL);
+        while id != NIL {
+            let key = keys.get(id).borrow();
+            let within = match bound {
+                Included(end) => key <= end,
+                Excluded(end) => key < end,
+                Unbounded => true,
+            };
+            if within {
+                found = id;
+                id = self.node(id).right;
+            } else {
+                id = self.node(id).left;
+            }
+        }
+        some(found)
+    }
+
+    // Where a range starts and ends. Panics on the same bounds `BTreeSet::range` does.
+    pub(crate) fn range<L, R, F, Q>(
+        &self,
+        keys: Keys<'_, L, R, F>,
+        start: Bound<&Q>,
+        end: Bound<&Q>,
+    ) -> Walk
+    where
+        F: Field<L, R>,
+        F::Key: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        if let (Included(s) | Excluded(s), Included(e) | Excluded(e)) = (start, end) {
+            match s.cmp(e) {
+                Ordering::Greater => panic!("range start is greater than range end"),
+                Ordering::Equal if matches!((start, end), (Excluded(_), Excluded(_))) => {
+                    panic!("range start and end are equal and excluded")
+                }
+                _ => {}
+            }
+        }
+        match (self.lower_bound(keys, start), self.upper_bound(keys, end)) {
+            // Both exist but the range between them is empty when the first slot after
+            // the start comes after the last one before the end
+            (Some(front), Some(back)) if keys.get(front).borrow() <= keys.get(back).borrow() => {
+                Walk::between(front, back)
+            }
+            _ => Walk::EMPTY,
+        }
+    }
+
+    // Adds `id` unless a slot with an equal key is already in the tree
+    pub(crate) fn insert<L, R, F>(&mut self, keys: Keys<'_, L, R, F>, id: Id) -> bool
+    where
+        F: Field<L, R>,
+        F::Key: Ord,
+    {
+        let key = keys.get(id);
+        let (mut parent, mut on_left, mut at) = (NIL, false, self.root);
+        while at != NIL {
+            parent = at;
+            (on_left, at) = match key.cmp(keys.get(at)) {
+                Ordering::Less => (true, self.node(at).left),
+                Ordering::Greater => (false, self.node(at).right),
+                Ordering::Equal => return false,
+            };
+        }
+
+        // Nothing is compared from here on
+        if self.nodes.len() <= id as usize {
+            self.nodes.resize(id as usize + 1, UNLINKED);
+        }
+        *self.node_mut(id) = Node { parent, ..UNLINKED };
+        if parent == NIL {
+            self.root = id;
+        } else if on_left {
+            self.node_mut(parent).left = id;
+        } else {
+            self.node_mut(parent).right = id;
+        }
+        while self.node(id).parent != NIL && priority(self.node(id).parent) < priority(id) {
+            self.rotate_up(id);
+        }
+        self.len += 1;
+        true
+    }
+
+    // Rotates `id` down to a leaf and cuts it off
+    pub(crate) fn remove(&mut self, id: Id) {
+        loop {
+            let Node { left, right, .. } = self.node(id);
+            let child = if left == NIL {
+                right
+            } else if right == NIL || priority(left) > priority(right) {
+                left
+            } else {
+                right
+            };
+            if child == NIL {
+                break;
+            }
+            self.rotate_up(child);
+        }
+        self.set_child(self.node(id).parent, id, NIL);
+        *self.node_mut(id) = UNLINKED;
+        self.len -= 1;
+    }
+
+    // Builds a tree from ids whose keys are strictly increasing, without comparing them.
+    // `spine` is the path from the root down its right edge so far.
+    pub(crate) fn from_sorted(ids: impl IntoIterator<Item = Id>) -> Self {
+        let mut tree = Tree::new();
+        let mut spine: Vec<Id> = Vec::new();
+        for id in ids {
+            if tree.nodes.len() <= id as usize {
+                tree.nodes.resize(id as usize + 1, UNLINKED);
+            }
+            let mut below = NIL;
+            while let Some(&top) = spine.last()
+                && priority(top) < priority(id)
+            {
+                below = top;
+                spine.pop();
+            }
+            let parent = spine.last().copied().unwrap_or(NIL);
+            *tree.node_mut(id) = Node {
+                parent,
+                left: below,
+                right: NIL,
+            };
+            if below != NIL {
+                tree.node_mut(below).parent = id;
+            }
+            if parent == NIL {
+                tree.root = id;
+            } else {
+                tree.node_mut(parent).right = id;
+            }
+            spine.push(id);
+            tree.len += 1;
+        }
+        tree
+    }
+
+    // Puts `id` in the place of its parent, which becomes its child
+    fn rotate_up(&mut self, id: Id) {
+        let parent = self.node(id).parent;
+        let grandparent = self.node(parent).parent;
+        if self.node(parent).left == id {
+            let moved = self.node(id).right;
+            self.node_mut(parent).left = moved;
+            self.node_mut(id).right = parent;
+            if moved != NIL {
+                self.node_mut(moved).parent = parent;
+            }
+        } else {
+            let moved = self.node(id).left;
+            self.node_mut(parent).right = moved;
+            self.node_mut(id).left = parent;
+            if moved != NIL {
+                self.node_mut(moved).parent = parent;
+            }
+        }
+        self.node_mut(parent).parent = id;
+        self.node_mut(id).parent = grandparent;
+        self.set_child(grandparent, parent, id);
+    }
+
+    // Makes `parent` point to `new` where it pointed to `old`
+    fn set_child(&mut self, parent: Id, old: Id, new: Id) {
+        if parent == NIL {
+            self.root = new;
+        } else if self.node(parent).left == old {
+            self.node_mut(parent).left = new;
+        } else {
+            self.node_mut(parent).right = new;
+        }
+    }
+}
+
+impl Default for Tree {
+    fn default() -> Self {
+        Tree::new()
+    }
+}
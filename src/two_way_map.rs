@@ -1,23 +1,151 @@
 use std::borrow::Borrow;
-use std::collections;
-use std::{fmt::Debug, ops::RangeBounds};
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt::{self, Debug, Display};
+use std::hash::{Hash, Hasher, RandomState};
+use std::ops::Bound::{self, Excluded, Included};
+use std::ops::RangeBounds;
+
+use crate::invariants::InvariantViolation;
+use crate::iter::{
+    IntoIter, IntoIterByRight, LeftRange, LeftValues, Pairs, PairsByRight, RightRange, RightValues,
+};
+use crate::side::{Hashed, Ids, Ordered, Side, SideIndex, SideKey, SideLookup, TreeRange};
+use crate::slab::{Id, Slab};
+use crate::table::Table;
+use crate::tree::Tree;
+use crate::unwind::Undo;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Overwritten<L, R> {
+    // Nothing was displaced
+    Neither,
+    // The pair that had the same left value
+    Left(L, R),
+    // The pair that had the same right value
+    Right(L, R),
+    // Exactly the same pair was already in the map
+    Pair(L, R),
+    // Two different pairs were displaced: the one with the same left value
+    // and the one with the same right value
+    Both((L, R), (L, R)),
+}
+
+impl<L, R> Overwritten<L, R> {
+    pub fn did_overwrite(&self) -> bool {
+        !matches!(self, Overwritten::Neither)
+    }
+}
+
+// Which values of a rejected pair were already mapped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conflict {
+    Left,
+    Right,
+    Both,
+}
+
+// The first pair `try_from_iter` or `try_extend` could not add, together with its
+// position in the input
+#[derive(Debug, PartialEq, Eq)]
+pub struct ConflictError<L, R> {
+    pub position: usize,
+    pub pair: (L, R),
+    pub conflict: Conflict,
+}
+
+impl<L: Debug, R: Debug> Display for ConflictError<L, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = match self.conflict {
+            Conflict::Left => "left value is",
+            Conflict::Right => "right value is",
+            Conflict::Both => "left and right values are",
+        };
+        write!(
+            f,
+            "pair {:?} at position {}: {side} already mapped",
+            self.pair, self.position
+        )
+    }
+}
+
+impl<L: Debug, R: Debug> Error for ConflictError<L, R> {}
+
+// Why `replace_left` or `replace_right` left the map unchanged.
+// Both variants give back the value that was meant to be the replacement.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReplaceError<T> {
+    // The value to replace isn't in the map
+    NotFound(T),
+    // The replacement is already mapped to another value
+    AlreadyMapped(T),
+}
+
+impl<T: Debug> Display for ReplaceError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplaceError::NotFound(value) => {
+                write!(f, "the value to replace with {value:?} is not mapped")
+            }
+            ReplaceError::AlreadyMapped(value) => write!(f, "{value:?} is already mapped"),
+        }
+    }
+}
 
-use crate::mem::{Rc, wrap_range, wrap_ref};
+impl<T: Debug> Error for ReplaceError<T> {}
 
-#[derive(Debug)]
-pub struct TwoWayMap<L, R> {
-    left_to_right: std::collections::BTreeMap<Rc<L>, Rc<R>>,
-    right_to_left: std::collections::BTreeMap<Rc<R>, Rc<L>>,
+// The pairs live in the slots of `slab`, and each index holds the ids of those slots. A
+// pair takes no allocation of its own: the slab and the indexes grow their vectors
+// geometrically and reuse what removed pairs left behind.
+pub struct TwoWayMap<L, R, LS: Side = Ordered, RS: Side = Ordered> {
+    pub(crate) slab: Slab<L, R>,
+    pub(crate) left_to_right: LS::Index,
+    pub(crate) right_to_left: RS::Index,
 }
 
+pub type HashTwoWayMap<L, R, H = RandomState> = TwoWayMap<L, R, Hashed<H>, Hashed<H>>;
+
 impl<L, R> TwoWayMap<L, R> {
     pub fn new() -> Self {
         Self {
-            left_to_right: std::collections::BTreeMap::new(),
-            right_to_left: std::collections::BTreeMap::new(),
+            slab: Slab::new(),
+            left_to_right: Tree::new(),
+            right_to_left: Tree::new(),
         }
     }
+}
 
+impl<L, R, H: Clone> TwoWayMap<L, R, Hashed<H>, Hashed<H>> {
+    pub fn with_hasher(hasher: H) -> Self {
+        Self {
+            slab: Slab::new(),
+            left_to_right: Table::with_hasher(hasher.clone()),
+            right_to_left: Table::with_hasher(hasher),
+        }
+    }
+}
+
+impl<L, R, H: Clone> TwoWayMap<L, R, Hashed<H>, Ordered> {
+    pub fn with_left_hasher(hasher: H) -> Self {
+        Self {
+            slab: Slab::new(),
+            left_to_right: Table::with_hasher(hasher),
+            right_to_left: Tree::new(),
+        }
+    }
+}
+
+impl<L, R, H: Clone> TwoWayMap<L, R, Ordered, Hashed<H>> {
+    pub fn with_right_hasher(hasher: H) -> Self {
+        Self {
+            slab: Slab::new(),
+            left_to_right: Tree::new(),
+            right_to_left: Table::with_hasher(hasher),
+        }
+    }
+}
+
+impl<L, R, LS: Side, RS: Side> TwoWayMap<L, R, LS, RS> {
     pub fn len(&self) -> usize {
         self.left_to_right.len()
     }
@@ -29,270 +157,711 @@ impl<L, R> TwoWayMap<L, R> {
     pub fn clear(&mut self) {
         self.left_to_right.clear();
         self.right_to_left.clear();
+        self.slab.clear();
+    }
+
+    // Moves all pairs out of the map, which is left empty
+    pub fn drain(&mut self) -> IntoIter<L, R, LS> {
+        let left_to_right = self.left_to_right.empty_like();
+        let right_to_left = self.right_to_left.empty_like();
+        IntoIter::new(TwoWayMap::<L, R, LS, RS> {
+            slab: std::mem::take(&mut self.slab),
+            left_to_right: std::mem::replace(&mut self.left_to_right, left_to_right),
+            right_to_left: std::mem::replace(&mut self.right_to_left, right_to_left),
+        })
     }
 }
 
-impl<L: Ord, R: Ord> TwoWayMap<L, R> {
-    pub fn insert(&mut self, left: L, right: R) {
-        // Check if left already exists
-        if let Some(existing_right) = self.left_to_right.get(wrap_ref(&left)) {
-            self.right_to_left.remove(existing_right);
-        }
-        // Check if right already exists
-        if let Some(existing_left) = self.right_to_left.get(wrap_ref(&right)) {
-            self.left_to_right.remove(existing_left);
+// `Ord`, `Eq`, `Hash` and `Clone` implementations may panic. If one does during `insert`,
+// `insert_no_overwrite`, `replace_left`, `replace_right`, `remove_by_left`,
+// `remove_by_right`, the pops, `retain` or `extract_if`, the map is left exactly as it was
+// before the call, and `clone` never changes the map it copies. Other operations leave the
+// map consistent, but may lose the pair they were working on: a `RightMut` or `LeftMut`
+// drops it if putting it back panics. A panic in the predicate of `retain` leaves the map
+// as it was as well.
+impl<L, R, LS: SideKey<L>, RS: SideKey<R>> TwoWayMap<L, R, LS, RS> {
+    pub fn insert(&mut self, left: L, right: R) -> Overwritten<L, R> {
+        let right_existed = self.contains_right(&right);
+
+        // Move out the pairs that collide on either side. They go back in if a comparison
+        // panics before the new pair is in.
+        let by_left = self.remove_by_left(&left);
+        let mut by_left = Undo::new(self, by_left, Self::restore);
+        let by_right = by_left
+            .target()
+            .remove_by_right(&right)
+            .map(|(right, left)| (left, right));
+        let mut by_right = Undo::new(by_left.target(), by_right, Self::restore);
+
+        by_right.target().attach(left, right);
+        let by_right = by_right.done();
+        let by_left = by_left.done();
+        self.debug_check_len();
+
+        match (by_left, by_right) {
+            (None, None) => Overwritten::Neither,
+            // `right` went away together with the pair found by `left`
+            (Some(pair), None) if right_existed => Overwritten::Pair(pair.0, pair.1),
+            (Some(pair), None) => Overwritten::Left(pair.0, pair.1),
+            (None, Some(pair)) => Overwritten::Right(pair.0, pair.1),
+            (Some(by_left), Some(by_right)) => Overwritten::Both(by_left, by_right),
         }
-
-        let left = Rc::new(left);
-        let right = Rc::new(right);
-
-        self.left_to_right.insert(left.clone(), right.clone());
-        self.right_to_left.insert(right, left);
     }
 
     pub fn insert_no_overwrite(&mut self, left: L, right: R) -> Result<(), (L, R)> {
         //Check if left or right already exists
-        if self.left_to_right.contains_key(wrap_ref(&left))
-            || self.right_to_left.contains_key(wrap_ref(&right))
-        {
+        if self.contains_left(&left) || self.contains_right(&right) {
             return Err((left, right));
         }
         self.insert(left, right);
         Ok(())
     }
 
-    pub fn remove_by_left<Q>(&mut self, left: &Q) -> Option<(L, R)>
+    // Like `extend`, but stops at the first pair whose left or right value is already
+    // mapped instead of overwriting. The pairs before it stay in the map.
+    pub fn try_extend<T>(&mut self, iter: T) -> Result<(), ConflictError<L, R>>
     where
-        L: Borrow<Q>,
-        Q: Ord + ?Sized,
+        T: IntoIterator<Item = (L, R)>,
     {
-        if let Some(right) = self.left_to_right.remove(wrap_ref(left)) {
-            let left = self.right_to_left.remove(&right).unwrap();
+        for (position, (left, right)) in iter.into_iter().enumerate() {
+            if let Err((left, right)) = self.insert_no_overwrite(left, right) {
+                let conflict = match (self.contains_left(&left), self.contains_right(&right)) {
+                    (true, true) => Conflict::Both,
+                    (true, false) => Conflict::Left,
+                    _ => Conflict::Right,
+                };
+                return Err(ConflictError {
+                    position,
+                    pair: (left, right),
+                    conflict,
+                });
+            }
+        }
+        self.debug_check();
+        Ok(())
+    }
 
-            // Convert Rc to L and R
-            let left = std::rc::Rc::try_unwrap(left.0).ok().unwrap();
-            let right = std::rc::Rc::try_unwrap(right.0).ok().unwrap();
+    pub fn try_from_iter<T>(iter: T) -> Result<Self, ConflictError<L, R>>
+    where
+        T: IntoIterator<Item = (L, R)>,
+        Self: Default,
+    {
+        let mut map = Self::default();
+        map.try_extend(iter)?;
+        Ok(map)
+    }
 
-            let pair = (left, right);
+    // Gives the pair of `old_left` the left value `new_left` and returns the old one
+    pub fn replace_left<Q>(&mut self, old_left: &Q, new_left: L) -> Result<L, ReplaceError<L>>
+    where
+        LS: SideLookup<L, Q>,
+        Q: ?Sized,
+    {
+        let Some(id) = LS::find(&self.left_to_right, self.slab.by_left(), old_left) else {
+            return Err(ReplaceError::NotFound(new_left));
+        };
+        // Replacing a value with an equal one is fine, any other match is a collision
+        if let Some(other) = LS::find(&self.left_to_right, self.slab.by_left(), &new_left)
+            && other != id
+        {
+            return Err(ReplaceError::AlreadyMapped(new_left));
+        }
 
-            return Some(pair);
+        // The slot keeps its place in the right index and only moves in the left one. If
+        // adding it back there panics, it goes back in with its old left value.
+        self.left_to_right.remove(id);
+        let old_left = std::mem::replace(self.slab.left_mut(id), new_left);
+        let mut old = Undo::new(self, (id, old_left), |map, (id, old_left)| {
+            *map.slab.left_mut(id) = old_left;
+            LS::insert(&mut map.left_to_right, map.slab.by_left(), id);
+        });
+        let map = old.target();
+        LS::insert(&mut map.left_to_right, map.slab.by_left(), id);
+        let (_, old_left) = old.done();
+        self.debug_check_len();
+
+        Ok(old_left)
+    }
+
+    pub fn replace_right<Q>(&mut self, old_right: &Q, new_right: R) -> Result<R, ReplaceError<R>>
+    where
+        RS: SideLookup<R, Q>,
+        Q: ?Sized,
+    {
+        let Some(id) = RS::find(&self.right_to_left, self.slab.by_right(), old_right) else {
+            return Err(ReplaceError::NotFound(new_right));
+        };
+        if let Some(other) = RS::find(&self.right_to_left, self.slab.by_right(), &new_right)
+            && other != id
+        {
+            return Err(ReplaceError::AlreadyMapped(new_right));
         }
-        None
+
+        self.right_to_left.remove(id);
+        let old_right = std::mem::replace(self.slab.right_mut(id), new_right);
+        let mut old = Undo::new(self, (id, old_right), |map, (id, old_right)| {
+            *map.slab.right_mut(id) = old_right;
+            RS::insert(&mut map.right_to_left, map.slab.by_right(), id);
+        });
+        let map = old.target();
+        RS::insert(&mut map.right_to_left, map.slab.by_right(), id);
+        let (_, old_right) = old.done();
+        self.debug_check_len();
+
+        Ok(old_right)
+    }
+
+    pub fn remove_by_left<Q>(&mut self, left: &Q) -> Option<(L, R)>
+    where
+        LS: SideLookup<L, Q>,
+        Q: ?Sized,
+    {
+        self.try_remove_by_left(left)
+            .unwrap_or_else(|violation| panic!("two_way_map: {violation}"))
     }
 
     pub fn remove_by_right<Q>(&mut self, right: &Q) -> Option<(R, L)>
     where
-        R: Borrow<Q>,
-        Q: Ord + ?Sized,
+        RS: SideLookup<R, Q>,
+        Q: ?Sized,
     {
-        if let Some(left) = self.right_to_left.remove(wrap_ref(right)) {
-            let right = self.left_to_right.remove(&left).unwrap().clone();
-            // Convert Rc to L and R
-            let left = std::rc::Rc::try_unwrap(left.0).ok().unwrap();
-            let right = std::rc::Rc::try_unwrap(right.0).ok().unwrap();
+        self.try_remove_by_right(right)
+            .unwrap_or_else(|violation| panic!("two_way_map: {violation}"))
+    }
 
-            let pair = (right, left);
+    // Like `remove_by_left`, but if the indexes don't agree about the pair it reports how
+    // and leaves it in the map instead of panicking
+    pub fn try_remove_by_left<Q>(&mut self, left: &Q) -> Result<Option<(L, R)>, InvariantViolation>
+    where
+        LS: SideLookup<L, Q>,
+        Q: ?Sized,
+    {
+        let Some(id) = LS::find(&self.left_to_right, self.slab.by_left(), left) else {
+            return Ok(None);
+        };
+        let pair = self.take_found_by_left(id)?;
+        self.debug_check_len();
+        Ok(Some(pair))
+    }
+
+    pub fn try_remove_by_right<Q>(
+        &mut self,
+        right: &Q,
+    ) -> Result<Option<(R, L)>, InvariantViolation>
+    where
+        RS: SideLookup<R, Q>,
+        Q: ?Sized,
+    {
+        let Some(id) = RS::find(&self.right_to_left, self.slab.by_right(), right) else {
+            return Ok(None);
+        };
+        let (left, right) = self.take_found_by_right(id)?;
+        self.debug_check_len();
+        Ok(Some((right, left)))
+    }
+
+    // Takes out the pair of a slot found through the left index, once looking it up in the
+    // right index leads to the same slot. Only that lookup compares or hashes anything, and
+    // it happens before the map changes.
+    pub(crate) fn take_found_by_left(&mut self, id: Id) -> Result<(L, R), InvariantViolation> {
+        self.check_partner_by_left(id)?;
+        Ok(self.detach(id))
+    }
+
+    pub(crate) fn take_found_by_right(&mut self, id: Id) -> Result<(L, R), InvariantViolation> {
+        self.check_partner_by_right(id)?;
+        Ok(self.detach(id))
+    }
+
+    // Puts a pair whose values aren't mapped yet into a new slot and adds it to both indexes
+    pub(crate) fn attach(&mut self, left: L, right: R) -> Id {
+        self.try_attach(left, right)
+            .unwrap_or_else(|_| panic!("two_way_map: attached a value that is already mapped"))
+    }
+
+    // Puts a pair into a new slot and adds it to both indexes, unless one of them already
+    // holds one of its values: then the slot is freed and the pair given back. Adding the
+    // slot to an index is what finds that out, so neither index is searched twice. If
+    // adding it to the left one panics, it's taken out of the right one again and the slot
+    // is freed.
+    pub(crate) fn try_attach(&mut self, left: L, right: R) -> Result<Id, (L, R)> {
+        let id = self.slab.insert(left, right);
+        let mut slot = Undo::new(self, id, |map, id| drop(map.slab.remove(id)));
+        let map = slot.target();
+        let attached = RS::insert(&mut map.right_to_left, map.slab.by_right(), id) && {
+            // Also taken back out if the left index turns the slot down
+            let mut added = Undo::new(map, id, |map, id| map.right_to_left.remove(id));
+            let map = added.target();
+            let attached = LS::insert(&mut map.left_to_right, map.slab.by_left(), id);
+            if attached {
+                added.done();
+            }
+            attached
+        };
+        let id = slot.done();
+        if attached {
+            return Ok(id);
+        }
+        Err(self
+            .slab
+            .remove(id)
+            .unwrap_or_else(|| panic!("two_way_map: {}", InvariantViolation::VacantSlot)))
+    }
 
-            return Some(pair);
+    pub(crate) fn restore(&mut self, pair: Option<(L, R)>) {
+        if let Some((left, right)) = pair {
+            self.attach(left, right);
         }
-        None
     }
 
     pub fn get_by_left<Q>(&self, left: &Q) -> Option<&R>
+    where
+        LS: SideLookup<L, Q>,
+        Q: ?Sized,
+    {
+        let id = LS::find(&self.left_to_right, self.slab.by_left(), left)?;
+        Some(self.slab.right(id))
+    }
+
+    pub fn get_by_right<Q>(&self, right: &Q) -> Option<&L>
+    where
+        RS: SideLookup<R, Q>,
+        Q: ?Sized,
+    {
+        let id = RS::find(&self.right_to_left, self.slab.by_right(), right)?;
+        Some(self.slab.left(id))
+    }
+
+    pub fn contains_left<Q>(&self, left: &Q) -> bool
+    where
+        LS: SideLookup<L, Q>,
+        Q: ?Sized,
+    {
+        LS::contains(&self.left_to_right, self.slab.by_left(), left)
+    }
+
+    pub fn contains_right<Q>(&self, right: &Q) -> bool
+    where
+        RS: SideLookup<R, Q>,
+        Q: ?Sized,
+    {
+        RS::contains(&self.right_to_left, self.slab.by_right(), right)
+    }
+
+    // Keeps the pairs for which `f` returns true. `f` is called exactly once per pair,
+    // in the order of the left index.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&L, &R) -> bool,
+    {
+        drop(self.detach_by_left(|left, right| !f(left, right)));
+    }
+
+    // Same as `retain`, but visits the pairs in the order of the right index
+    pub fn retain_by_right<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&L, &R) -> bool,
+    {
+        drop(self.detach_by_right(|left, right| !f(left, right)));
+    }
+
+    // Removes the pairs for which `f` returns true and returns them, in the order of
+    // the left index
+    pub fn extract_if<F>(&mut self, f: F) -> Vec<(L, R)>
+    where
+        F: FnMut(&L, &R) -> bool,
+    {
+        self.detach_by_left(f)
+    }
+
+    // Takes the pairs for which `f` returns true out of the map, deciding in a single pass
+    // over the left index. Nothing changes until `f` has seen every pair, and the slots are
+    // then taken out by id, which compares and hashes nothing, so neither a panic in `f`
+    // nor one in `Ord` or `Hash` can leave the map half done.
+    fn detach_by_left<F>(&mut self, mut f: F) -> Vec<(L, R)>
+    where
+        F: FnMut(&L, &R) -> bool,
+    {
+        let ids: Vec<Id> = Ids::new(&self.left_to_right)
+            .filter(|&id| {
+                let (left, right) = self.slab.pair(id);
+                f(left, right)
+            })
+            .collect();
+        self.detach_all(ids)
+    }
+
+    pub(crate) fn detach_by_right<F>(&mut self, mut f: F) -> Vec<(L, R)>
+    where
+        F: FnMut(&L, &R) -> bool,
+    {
+        let ids: Vec<Id> = Ids::new(&self.right_to_left)
+            .filter(|&id| {
+                let (left, right) = self.slab.pair(id);
+                f(left, right)
+            })
+            .collect();
+        self.detach_all(ids)
+    }
+
+    fn detach_all(&mut self, ids: Vec<Id>) -> Vec<(L, R)> {
+        if ids.is_empty() {
+            return Vec::new();
+        }
+        let detached = ids.into_iter().map(|id| self.detach(id)).collect();
+        self.debug_check();
+        detached
+    }
+}
+
+impl<L, R, LS: Side, RS: Side> TwoWayMap<L, R, LS, RS> {
+    pub fn pairs(&self) -> Pairs<'_, L, R, LS> {
+        Pairs::new(&self.slab, Ids::new(&self.left_to_right))
+    }
+
+    // The pairs in the order of the right index, in the same `(left, right)` shape as `pairs`
+    pub fn pairs_by_right(&self) -> PairsByRight<'_, L, R, RS> {
+        PairsByRight::new(&self.slab, Ids::new(&self.right_to_left))
+    }
+
+    pub fn into_iter_by_right(self) -> IntoIterByRight<L, R, RS> {
+        IntoIterByRight::new(self)
+    }
+
+    pub fn left_values(&self) -> LeftValues<'_, L, R, LS> {
+        LeftValues::new(&self.slab, Ids::new(&self.left_to_right))
+    }
+
+    pub fn right_values(&self) -> RightValues<'_, L, R, RS> {
+        RightValues::new(&self.slab, Ids::new(&self.right_to_left))
+    }
+
+    // Takes slot `id` out of both indexes and frees it, without comparing or hashing
+    pub(crate) fn detach(&mut self, id: Id) -> (L, R) {
+        self.left_to_right.remove(id);
+        self.right_to_left.remove(id);
+        self.slab
+            .remove(id)
+            .unwrap_or_else(|| panic!("two_way_map: {}", InvariantViolation::VacantSlot))
+    }
+}
+
+impl<L: Ord, R, RS: Side> TwoWayMap<L, R, Ordered, RS> {
+    pub fn left_range<T>(&self, range: T) -> LeftRange<'_, L, R>
+    where
+        T: RangeBounds<L>,
+    {
+        self.left_range_in(range.start_bound(), range.end_bound())
+    }
+
+    // Takes bounds that are references to any type `L` borrows as, so that `"a".."m"` can
+    // be used on `String` values without allocating
+    pub fn left_range_borrowed<'q, Q>(&self, range: impl RangeBounds<&'q Q>) -> LeftRange<'_, L, R>
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized + 'q,
+    {
+        let start = range.start_bound().map(|start| *start);
+        self.left_range_in(start, range.end_bound().map(|end| *end))
+    }
+
+    fn left_range_in<Q>(&self, start: Bound<&Q>, end: Bound<&Q>) -> LeftRange<'_, L, R>
     where
         L: Borrow<Q>,
         Q: Ord + ?Sized,
     {
-        if let Some(right) = self.left_to_right.get(wrap_ref(left)) {
-            return Some(right.as_ref());
-        }
-        None
+        let walk = self.left_to_right.range(self.slab.by_left(), start, end);
+        LeftRange::new(&self.slab, TreeRange::new(&self.left_to_right, walk))
     }
 
-    pub fn get_by_right<Q>(&self, right: &Q) -> Option<&L>
+    pub fn first_by_left(&self) -> Option<(&L, &R)> {
+        let id = self.left_to_right.first()?;
+        Some(self.slab.pair(id))
+    }
+
+    pub fn last_by_left(&self) -> Option<(&L, &R)> {
+        let id = self.left_to_right.last()?;
+        Some(self.slab.pair(id))
+    }
+
+    // The pair with the greatest left value at or below `left`. `ceiling_left` is the
+    // smallest at or above it, `lower_left` and `higher_left` leave out `left` itself.
+    pub fn floor_left<Q>(&self, left: &Q) -> Option<(&L, &R)>
     where
-        R: Borrow<Q>,
+        L: Borrow<Q>,
         Q: Ord + ?Sized,
     {
-        if let Some(left) = self.right_to_left.get(wrap_ref(right)) {
-            return Some(left.as_ref());
-        }
-        None
+        let keys = self.slab.by_left();
+        let id = self.left_to_right.upper_bound(keys, Included(left))?;
+        Some(self.slab.pair(id))
     }
 
-    pub fn contains_left<Q>(&self, left: &Q) -> bool
+    pub fn ceiling_left<Q>(&self, left: &Q) -> Option<(&L, &R)>
     where
         L: Borrow<Q>,
         Q: Ord + ?Sized,
     {
-        self.left_to_right.contains_key(wrap_ref(left))
+        let keys = self.slab.by_left();
+        let id = self.left_to_right.lower_bound(keys, Included(left))?;
+        Some(self.slab.pair(id))
     }
 
-    pub fn contains_right<Q>(&self, right: &Q) -> bool
+    pub fn lower_left<Q>(&self, left: &Q) -> Option<(&L, &R)>
     where
-        R: Borrow<Q>,
+        L: Borrow<Q>,
         Q: Ord + ?Sized,
     {
-        self.right_to_left.contains_key(wrap_ref(right))
+        let keys = self.slab.by_left();
+        let id = self.left_to_right.upper_bound(keys, Excluded(left))?;
+        Some(self.slab.pair(id))
+    }
+
+    pub fn higher_left<Q>(&self, left: &Q) -> Option<(&L, &R)>
+    where
+        L: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let keys = self.slab.by_left();
+        let id = self.left_to_right.lower_bound(keys, Excluded(left))?;
+        Some(self.slab.pair(id))
     }
 }
 
-impl<L, R> TwoWayMap<L, R> {
-    pub fn pairs(&self) -> impl Iterator<Item = (&L, &R)> {
-        self.left_to_right.iter().map(|(left, right)| {
-            let left = left.as_ref();
-            let right = right.as_ref();
-            (left, right)
-        })
+// A pop finds its slot without looking at any value, so the other index still has to be
+// asked whether it agrees before the pair is taken out
+impl<L: Ord, R, RS: SideKey<R>> TwoWayMap<L, R, Ordered, RS> {
+    pub fn pop_first_by_left(&mut self) -> Option<(L, R)> {
+        self.try_pop_first_by_left()
+            .unwrap_or_else(|violation| panic!("two_way_map: {violation}"))
+    }
+
+    pub fn pop_last_by_left(&mut self) -> Option<(L, R)> {
+        self.try_pop_last_by_left()
+            .unwrap_or_else(|violation| panic!("two_way_map: {violation}"))
+    }
+
+    // Like the pops, but if the indexes don't agree about the pair they report how and
+    // leave it in the map instead of panicking
+    pub fn try_pop_first_by_left(&mut self) -> Result<Option<(L, R)>, InvariantViolation> {
+        let first = self.left_to_right.first();
+        first.map(|id| self.take_popped_by_left(id)).transpose()
     }
 
-    pub fn left_values(&self) -> impl Iterator<Item = &L> {
-        self.left_to_right.keys().map(|k| k.as_ref())
+    pub fn try_pop_last_by_left(&mut self) -> Result<Option<(L, R)>, InvariantViolation> {
+        let last = self.left_to_right.last();
+        last.map(|id| self.take_popped_by_left(id)).transpose()
     }
 
-    pub fn right_values(&self) -> impl Iterator<Item = &R> {
-        self.right_to_left.keys().map(|k| k.as_ref())
+    fn take_popped_by_left(&mut self, id: Id) -> Result<(L, R), InvariantViolation> {
+        let pair = self.take_found_by_left(id)?;
+        self.debug_check_len();
+        Ok(pair)
     }
 }
 
-impl<L: Ord, R: Ord> TwoWayMap<L, R> {
-    pub fn left_range<T>(&self, range: T) -> impl Iterator<Item = (&L, &R)>
+impl<L, R: Ord, LS: Side> TwoWayMap<L, R, LS, Ordered> {
+    pub fn right_range<T>(&self, range: T) -> RightRange<'_, L, R>
     where
-        T: RangeBounds<L>,
+        T: RangeBounds<R>,
     {
-        self.left_to_right
-            .range(wrap_range(&range))
-            .map(|(left, right)| (left.as_ref(), right.as_ref()))
+        self.right_range_in(range.start_bound(), range.end_bound())
     }
 
-    pub fn right_range<T>(&self, range: T) -> impl Iterator<Item = (&R, &L)>
+    // See `left_range_borrowed`
+    pub fn right_range_borrowed<'q, Q>(
+        &self,
+        range: impl RangeBounds<&'q Q>,
+    ) -> RightRange<'_, L, R>
     where
-        T: RangeBounds<R>,
+        R: Borrow<Q>,
+        Q: Ord + ?Sized + 'q,
     {
-        self.right_to_left
-            .range(wrap_range(&range))
-            .map(|(right, left)| (right.as_ref(), left.as_ref()))
+        let start = range.start_bound().map(|start| *start);
+        self.right_range_in(start, range.end_bound().map(|end| *end))
     }
 
-    pub fn retain<F>(&mut self, mut f: F)
+    fn right_range_in<Q>(&self, start: Bound<&Q>, end: Bound<&Q>) -> RightRange<'_, L, R>
     where
-        F: FnMut(&L, &R) -> bool,
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let walk = self.right_to_left.range(self.slab.by_right(), start, end);
+        RightRange::new(&self.slab, TreeRange::new(&self.right_to_left, walk))
+    }
+
+    pub fn first_by_right(&self) -> Option<(&L, &R)> {
+        let id = self.right_to_left.first()?;
+        Some(self.slab.pair(id))
+    }
+
+    pub fn last_by_right(&self) -> Option<(&L, &R)> {
+        let id = self.right_to_left.last()?;
+        Some(self.slab.pair(id))
+    }
+
+    // Same as for the left side, still returning `(left, right)`
+    pub fn floor_right<Q>(&self, right: &Q) -> Option<(&L, &R)>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let keys = self.slab.by_right();
+        let id = self.right_to_left.upper_bound(keys, Included(right))?;
+        Some(self.slab.pair(id))
+    }
+
+    pub fn ceiling_right<Q>(&self, right: &Q) -> Option<(&L, &R)>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let keys = self.slab.by_right();
+        let id = self.right_to_left.lower_bound(keys, Included(right))?;
+        Some(self.slab.pair(id))
+    }
+
+    pub fn lower_right<Q>(&self, right: &Q) -> Option<(&L, &R)>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let keys = self.slab.by_right();
+        let id = self.right_to_left.upper_bound(keys, Excluded(right))?;
+        Some(self.slab.pair(id))
+    }
+
+    pub fn higher_right<Q>(&self, right: &Q) -> Option<(&L, &R)>
+    where
+        R: Borrow<Q>,
+        Q: Ord + ?Sized,
     {
-        self.left_to_right
-            .retain(|left, right| f(left.0.as_ref(), right.0.as_ref()));
+        let keys = self.slab.by_right();
+        let id = self.right_to_left.lower_bound(keys, Excluded(right))?;
+        Some(self.slab.pair(id))
+    }
+}
 
-        self.right_to_left
-            .retain(|right, left| f(left.0.as_ref(), right.0.as_ref()));
+impl<L, R: Ord, LS: SideKey<L>> TwoWayMap<L, R, LS, Ordered> {
+    pub fn pop_first_by_right(&mut self) -> Option<(R, L)> {
+        self.try_pop_first_by_right()
+            .unwrap_or_else(|violation| panic!("two_way_map: {violation}"))
+    }
+
+    pub fn pop_last_by_right(&mut self) -> Option<(R, L)> {
+        self.try_pop_last_by_right()
+            .unwrap_or_else(|violation| panic!("two_way_map: {violation}"))
+    }
+
+    pub fn try_pop_first_by_right(&mut self) -> Result<Option<(R, L)>, InvariantViolation> {
+        let first = self.right_to_left.first();
+        first.map(|id| self.take_popped_by_right(id)).transpose()
+    }
+
+    pub fn try_pop_last_by_right(&mut self) -> Result<Option<(R, L)>, InvariantViolation> {
+        let last = self.right_to_left.last();
+        last.map(|id| self.take_popped_by_right(id)).transpose()
+    }
+
+    fn take_popped_by_right(&mut self, id: Id) -> Result<(R, L), InvariantViolation> {
+        let (left, right) = self.take_found_by_right(id)?;
+        self.debug_check_len();
+        Ok((right, left))
     }
 }
 
-impl<L, R> Default for TwoWayMap<L, R> {
+impl<L, R, LS: Side, RS: Side> Default for TwoWayMap<L, R, LS, RS>
+where
+    LS::Index: Default,
+    RS::Index: Default,
+{
     fn default() -> Self {
-        Self::new()
+        Self {
+            slab: Slab::new(),
+            left_to_right: Default::default(),
+            right_to_left: Default::default(),
+        }
     }
 }
 
-impl<L: Ord, R: Ord> Clone for TwoWayMap<L, R>
+// The slots are copied as they are, so the indexes stay valid without comparing or
+// hashing anything. If a clone panics, `self` is untouched and the partial copy is dropped.
+impl<L, R, LS: Side, RS: Side> Clone for TwoWayMap<L, R, LS, RS>
 where
     L: Clone,
     R: Clone,
 {
     fn clone(&self) -> Self {
-        let mut other = TwoWayMap::new();
-        for (left, right) in self.left_to_right.iter() {
-            let left = left.0.as_ref().clone();
-            let right = right.0.as_ref().clone();
-            other.insert(left, right);
+        TwoWayMap {
+            slab: self.slab.clone(),
+            left_to_right: self.left_to_right.clone(),
+            right_to_left: self.right_to_left.clone(),
         }
-        other
     }
 }
 
-impl<L: Ord, R: Ord> Extend<(L, R)> for TwoWayMap<L, R> {
-    fn extend<T: IntoIterator<Item = (L, R)>>(&mut self, iter: T) {
-        for (left, right) in iter {
-            self.insert(left, right);
-        }
+impl<L: Debug, R: Debug, LS: Side, RS: Side> Debug for TwoWayMap<L, R, LS, RS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.pairs()).finish()
     }
 }
 
-impl<L: Ord, R: Ord> FromIterator<(L, R)> for TwoWayMap<L, R> {
-    fn from_iter<T: IntoIterator<Item = (L, R)>>(iter: T) -> Self {
-        let mut map = TwoWayMap::new();
-        for (left, right) in iter {
-            map.insert(left, right);
-        }
-        map
+// Two maps are equal when they hold the same pairs, whatever order they were inserted in.
+// Each left value is looked up in the other map, so that works for hashed sides too.
+impl<L, R, LS: SideKey<L>, RS: SideKey<R>> PartialEq for TwoWayMap<L, R, LS, RS>
+where
+    R: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .pairs()
+                .all(|(left, right)| other.get_by_left(left) == Some(right))
     }
 }
 
-pub struct IntoIter<L, R> {
-    left_to_right_iter: std::collections::btree_map::IntoIter<Rc<L>, Rc<R>>,
-}
+impl<L, R: Eq, LS: SideKey<L>, RS: SideKey<R>> Eq for TwoWayMap<L, R, LS, RS> {}
 
-impl<L, R> IntoIter<L, R> {
-    fn new(map: TwoWayMap<L, R>) -> Self {
-        Self {
-            left_to_right_iter: map.left_to_right.into_iter(),
+// Hashing and ordering need the pairs in an order that doesn't depend on how the map was
+// built, which only an `Ordered` left side gives, the same way `BTreeMap` implements them
+// and `HashMap` doesn't. Maps compare lexicographically by their pairs in left order.
+impl<L: Hash, R: Hash, RS: Side> Hash for TwoWayMap<L, R, Ordered, RS> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len().hash(state);
+        for pair in self.pairs() {
+            pair.hash(state);
         }
     }
 }
 
-impl<L, R> Iterator for IntoIter<L, R> {
-    type Item = (L, R);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if let Some((left, right)) = self.left_to_right_iter.next() {
-            let left = std::rc::Rc::try_unwrap(left.0).ok().unwrap();
-            let right = std::rc::Rc::try_unwrap(right.0).ok().unwrap();
-
-            return Some((left, right));
-        }
-        None
+impl<L: Ord, R: PartialOrd, RS: SideKey<R>> PartialOrd for TwoWayMap<L, R, Ordered, RS> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.pairs().partial_cmp(other.pairs())
     }
 }
 
-impl<L, R> IntoIterator for TwoWayMap<L, R> {
-    type Item = (L, R);
-
-    type IntoIter = IntoIter<L, R>;
-
-    fn into_iter(self) -> Self::IntoIter {
-        IntoIter::new(self)
+impl<L: Ord, R: Ord, RS: SideKey<R>> Ord for TwoWayMap<L, R, Ordered, RS> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.pairs().cmp(other.pairs())
     }
 }
 
-pub struct RefIter<'l, L, R> {
-    iter: collections::btree_map::Iter<'l, Rc<L>, Rc<R>>,
-}
-
-impl<'l, L, R> RefIter<'l, L, R> {
-    fn new(map_ref: &'l TwoWayMap<L, R>) -> Self {
-        Self {
-            iter: map_ref.left_to_right.iter(),
+impl<L, R, LS: SideKey<L>, RS: SideKey<R>> Extend<(L, R)> for TwoWayMap<L, R, LS, RS> {
+    fn extend<T: IntoIterator<Item = (L, R)>>(&mut self, iter: T) {
+        for (left, right) in iter {
+            self.insert(left, right);
         }
     }
 }
 
-impl<'l, L, R> Iterator for RefIter<'l, L, R> {
-    type Item = (&'l L, &'l R);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if let Some((left, right)) = self.iter.next() {
-            let left = left.as_ref();
-            let right = right.as_ref();
-            return Some((left, right));
+impl<L, R, LS: SideKey<L>, RS: SideKey<R>> FromIterator<(L, R)> for TwoWayMap<L, R, LS, RS>
+where
+    Self: Default,
+{
+    fn from_iter<T: IntoIterator<Item = (L, R)>>(iter: T) -> Self {
+        let mut map = TwoWayMap::default();
+        for (left, right) in iter {
+            map.insert(left, right);
         }
-        None
-    }
-}
-
-impl<'l, L, R> IntoIterator for &'l TwoWayMap<L, R> {
-    type Item = (&'l L, &'l R);
-
-    type IntoIter = RefIter<'l, L, R>;
-
-    fn into_iter(self) -> Self::IntoIter {
-        RefIter::new(self)
+        map
     }
 }
@@ -0,0 +1,53 @@
+use std::ops::Deref;
+
+// Operations that change both indexes compare or hash values in between, and `Ord`, `Eq`
+// and `Hash` implementations are allowed to panic. An `Undo` holds a value taken out of
+// `target` (or a handle to one just put into it) and reverts that change when it's dropped
+// before `done`, so that a panic in a later step unwinds through it and leaves the map
+// the way it was. Reverting compares and hashes the same values again; if that panics
+// too, the process aborts like it does for any panic during unwinding.
+pub(crate) struct Undo<'a, S, T> {
+    target: &'a mut S,
+    value: Option<T>,
+    undo: fn(&mut S, T),
+}
+
+impl<'a, S, T> Undo<'a, S, T> {
+    pub(crate) fn new(target: &'a mut S, value: T, undo: fn(&mut S, T)) -> Self {
+        Self {
+            target,
+            value: Some(value),
+            undo,
+        }
+    }
+
+    pub(crate) fn target(&mut self) -> &mut S {
+        self.target
+    }
+
+    // Both at once, to move things between them
+    pub(crate) fn split(&mut self) -> (&mut S, &mut T) {
+        (self.target, self.value.as_mut().unwrap())
+    }
+
+    // Keeps the change and gives back the value
+    pub(crate) fn done(mut self) -> T {
+        self.value.take().unwrap()
+    }
+}
+
+impl<S, T> Deref for Undo<'_, S, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value.as_ref().unwrap()
+    }
+}
+
+impl<S, T> Drop for Undo<'_, S, T> {
+    fn drop(&mut self) {
+        if let Some(value) = self.value.take() {
+            (self.undo)(self.target, value);
+        }
+    }
+}
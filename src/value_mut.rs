@@ -0,0 +1,207 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::side::{Ordered, SideIndex, SideKey, SideLookup};
+use crate::slab::Id;
+use crate::two_way_map::{Overwritten, TwoWayMap};
+use crate::unwind::Undo;
+
+// A guard holds the slot of a pair after taking it out of the index of the value it hands
+// out, so that value can be changed in place in the slab while the other index keeps the
+// slot the whole time. The slot goes back into that index, under the new value, when the
+// guard is dropped or committed. The value is cloned the first time it's borrowed mutably,
+// so that a guard that's dropped with a value that is already mapped can put the original
+// back instead. `commit` rather displaces the pair that had the value the same way
+// `insert` does it, and returns it.
+#[must_use = "the pair is put back when the guard is dropped or committed"]
+pub struct RightMut<'a, L, R, LS: SideKey<L> = Ordered, RS: SideKey<R> = Ordered> {
+    map: &'a mut TwoWayMap<L, R, LS, RS>,
+    id: Option<Id>,
+    original: Option<R>,
+}
+
+#[must_use = "the pair is put back when the guard is dropped or committed"]
+pub struct LeftMut<'a, L, R, LS: SideKey<L> = Ordered, RS: SideKey<R> = Ordered> {
+    map: &'a mut TwoWayMap<L, R, LS, RS>,
+    id: Option<Id>,
+    original: Option<L>,
+}
+
+impl<L, R, LS: SideKey<L>, RS: SideKey<R>> TwoWayMap<L, R, LS, RS> {
+    pub fn get_by_left_mut<Q>(&mut self, left: &Q) -> Option<RightMut<'_, L, R, LS, RS>>
+    where
+        LS: SideLookup<L, Q>,
+        Q: ?Sized,
+    {
+        let id = LS::find(&self.left_to_right, self.slab.by_left(), left)?;
+        self.check_partner_by_left(id)
+            .unwrap_or_else(|violation| panic!("two_way_map: {violation}"));
+        self.right_to_left.remove(id);
+        Some(RightMut {
+            map: self,
+            id: Some(id),
+            original: None,
+        })
+    }
+
+    pub fn get_by_right_mut<Q>(&mut self, right: &Q) -> Option<LeftMut<'_, L, R, LS, RS>>
+    where
+        RS: SideLookup<R, Q>,
+        Q: ?Sized,
+    {
+        let id = RS::find(&self.right_to_left, self.slab.by_right(), right)?;
+        self.check_partner_by_right(id)
+            .unwrap_or_else(|violation| panic!("two_way_map: {violation}"));
+        self.left_to_right.remove(id);
+        Some(LeftMut {
+            map: self,
+            id: Some(id),
+            original: None,
+        })
+    }
+
+    // Puts a slot that only the left index kept back into the right one, unless another
+    // slot already has its right value. If that panics, the pair leaves the map.
+    fn reattach_right(&mut self, id: Id) -> bool {
+        let mut slot = Undo::new(self, id, |map, id| {
+            map.left_to_right.remove(id);
+            drop(map.slab.remove(id));
+        });
+        let map = slot.target();
+        let attached = RS::insert(&mut map.right_to_left, map.slab.by_right(), id);
+        slot.done();
+        attached
+    }
+
+    fn reattach_left(&mut self, id: Id) -> bool {
+        let mut slot = Undo::new(self, id, |map, id| {
+            map.right_to_left.remove(id);
+            drop(map.slab.remove(id));
+        });
+        let map = slot.target();
+        let attached = LS::insert(&mut map.left_to_right, map.slab.by_left(), id);
+        slot.done();
+        attached
+    }
+}
+
+impl<L, R, LS: SideKey<L>, RS: SideKey<R>> RightMut<'_, L, R, LS, RS> {
+    pub fn left(&self) -> &L {
+        self.map.slab.left(self.id.unwrap())
+    }
+
+    // Puts the pair back and returns the pair it displaced, if the new right value
+    // was already mapped
+    pub fn commit(mut self) -> Overwritten<L, R> {
+        let Some(id) = self.id.take() else {
+            return Overwritten::Neither;
+        };
+        let map = &mut *self.map;
+        let found = RS::find(&map.right_to_left, map.slab.by_right(), map.slab.right(id));
+        let displaced = found.map(|other| {
+            map.take_found_by_right(other)
+                .unwrap_or_else(|violation| panic!("two_way_map: {violation}"))
+        });
+        // The displaced pair goes back in if the slot can't
+        let mut displaced = Undo::new(map, displaced, TwoWayMap::restore);
+        displaced.target().reattach_right(id);
+        let displaced = displaced.done();
+        self.map.debug_check_len();
+
+        match displaced {
+            Some((left, right)) => Overwritten::Right(left, right),
+            None => Overwritten::Neither,
+        }
+    }
+}
+
+impl<L, R, LS: SideKey<L>, RS: SideKey<R>> Deref for RightMut<'_, L, R, LS, RS> {
+    type Target = R;
+
+    fn deref(&self) -> &R {
+        self.map.slab.right(self.id.unwrap())
+    }
+}
+
+impl<L, R: Clone, LS: SideKey<L>, RS: SideKey<R>> DerefMut for RightMut<'_, L, R, LS, RS> {
+    fn deref_mut(&mut self) -> &mut R {
+        let right = self.map.slab.right_mut(self.id.unwrap());
+        self.original.get_or_insert_with(|| right.clone());
+        right
+    }
+}
+
+impl<L, R, LS: SideKey<L>, RS: SideKey<R>> Drop for RightMut<'_, L, R, LS, RS> {
+    fn drop(&mut self) {
+        let Some(id) = self.id.take() else {
+            return;
+        };
+        // The left index never let go of the slot, so only the right value can collide
+        if !self.map.reattach_right(id)
+            && let Some(original) = self.original.take()
+        {
+            *self.map.slab.right_mut(id) = original;
+            self.map.reattach_right(id);
+        }
+        self.map.debug_check_len();
+    }
+}
+
+impl<L, R, LS: SideKey<L>, RS: SideKey<R>> LeftMut<'_, L, R, LS, RS> {
+    pub fn right(&self) -> &R {
+        self.map.slab.right(self.id.unwrap())
+    }
+
+    // Puts the pair back and returns the pair it displaced, if the new left value
+    // was already mapped
+    pub fn commit(mut self) -> Overwritten<L, R> {
+        let Some(id) = self.id.take() else {
+            return Overwritten::Neither;
+        };
+        let map = &mut *self.map;
+        let found = LS::find(&map.left_to_right, map.slab.by_left(), map.slab.left(id));
+        let displaced = found.map(|other| {
+            map.take_found_by_left(other)
+                .unwrap_or_else(|violation| panic!("two_way_map: {violation}"))
+        });
+        let mut displaced = Undo::new(map, displaced, TwoWayMap::restore);
+        displaced.target().reattach_left(id);
+        let displaced = displaced.done();
+        self.map.debug_check_len();
+
+        match displaced {
+            Some((left, right)) => Overwritten::Left(left, right),
+            None => Overwritten::Neither,
+        }
+    }
+}
+
+impl<L, R, LS: SideKey<L>, RS: SideKey<R>> Deref for LeftMut<'_, L, R, LS, RS> {
+    type Target = L;
+
+    fn deref(&self) -> &L {
+        self.map.slab.left(self.id.unwrap())
+    }
+}
+
+impl<L: Clone, R, LS: SideKey<L>, RS: SideKey<R>> DerefMut for LeftMut<'_, L, R, LS, RS> {
+    fn deref_mut(&mut self) -> &mut L {
+        let left = self.map.slab.left_mut(self.id.unwrap());
+        self.original.get_or_insert_with(|| left.clone());
+        left
+    }
+}
+
+impl<L, R, LS: SideKey<L>, RS: SideKey<R>> Drop for LeftMut<'_, L, R, LS, RS> {
+    fn drop(&mut self) {
+        let Some(id) = self.id.take() else {
+            return;
+        };
+        if !self.map.reattach_left(id)
+            && let Some(original) = self.original.take()
+        {
+            *self.map.slab.left_mut(id) = original;
+            self.map.reattach_left(id);
+        }
+        self.map.debug_check_len();
+    }
+}
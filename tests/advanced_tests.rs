@@ -87,3 +87,1582 @@ mod test_borrow_trait {
         assert_eq!(map.get_by_left("foo"), Some(&String::from("bar")));
     }
 }
+
+#[cfg(feature = "test-sync")]
+mod test_sync {
+    use std::sync::{Arc, RwLock};
+    use std::thread;
+
+    use two_way_map::TwoWayMap;
+
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+
+    #[test]
+    fn test_send_sync_bounds() {
+        assert_send::<TwoWayMap<i32, String>>();
+        assert_sync::<TwoWayMap<i32, String>>();
+        assert_send::<TwoWayMap<String, Vec<u8>>>();
+        assert_sync::<TwoWayMap<String, Vec<u8>>>();
+        assert_send::<RwLock<TwoWayMap<u64, String>>>();
+        assert_sync::<RwLock<TwoWayMap<u64, String>>>();
+    }
+
+    #[test]
+    fn test_move_to_thread() {
+        let mut map = TwoWayMap::new();
+        map.insert(1, String::from("one"));
+        map.insert(2, String::from("two"));
+
+        let map = thread::spawn(move || {
+            map.insert(3, String::from("three"));
+            map
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get_by_right("three"), Some(&3));
+    }
+
+    #[test]
+    fn test_shared_behind_rw_lock() {
+        let map = Arc::new(RwLock::new(TwoWayMap::new()));
+
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let map = Arc::clone(&map);
+                thread::spawn(move || {
+                    map.write().unwrap().insert(i, i.to_string());
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let map = map.read().unwrap();
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.get_by_left(&2), Some(&String::from("2")));
+        assert_eq!(map.get_by_right("3"), Some(&3));
+    }
+}
+
+#[cfg(feature = "test-entry")]
+mod test_entry {
+    use two_way_map::{LeftEntry, RightEntry, TwoWayMap};
+
+    #[test]
+    fn test_left_entry_or_insert() {
+        let mut map = TwoWayMap::new();
+        map.insert(1, 2);
+
+        assert_eq!(map.left_entry(1).or_insert(10), Ok(&2));
+        assert_eq!(map.left_entry(3).or_insert(4), Ok(&4));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get_by_right(&4), Some(&3));
+
+        // 2 is already mapped to 1, so the vacant entry refuses it
+        assert_eq!(map.left_entry(5).or_insert(2), Err((5, 2)));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get_by_left(&1), Some(&2));
+        assert_eq!(map.get_by_left(&5), None);
+    }
+
+    #[test]
+    fn test_left_entry_or_insert_with_is_lazy() {
+        let mut map = TwoWayMap::new();
+        map.insert(String::from("hello"), String::from("world"));
+
+        let result = map
+            .left_entry(String::from("hello"))
+            .or_insert_with(|| unreachable!());
+        assert_eq!(result, Ok(&String::from("world")));
+
+        let result = map
+            .left_entry(String::from("foo"))
+            .or_insert_with(|| String::from("bar"));
+        assert_eq!(result, Ok(&String::from("bar")));
+        assert_eq!(map.get_by_right("bar"), Some(&String::from("foo")));
+    }
+
+    #[test]
+    fn test_left_entry_variants() {
+        let mut map = TwoWayMap::new();
+        map.insert(1, 2);
+
+        match map.left_entry(1) {
+            LeftEntry::Occupied(entry) => {
+                assert_eq!(entry.left(), &1);
+                assert_eq!(entry.get(), &2);
+                assert_eq!(entry.remove(), (1, 2));
+            }
+            LeftEntry::Vacant(_) => panic!("expected an occupied entry"),
+        }
+        assert!(map.is_empty());
+
+        match map.left_entry(1) {
+            LeftEntry::Occupied(_) => panic!("expected a vacant entry"),
+            LeftEntry::Vacant(entry) => {
+                assert_eq!(entry.left(), &1);
+                assert_eq!(entry.into_left(), 1);
+            }
+        }
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_right_entry() {
+        let mut map = TwoWayMap::new();
+        map.insert(1, 2);
+
+        assert_eq!(map.right_entry(2).or_insert(10), Ok(&1));
+        assert_eq!(map.right_entry(4).or_insert(3), Ok(&3));
+        assert_eq!(map.right_entry(6).or_insert(1), Err((6, 1)));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get_by_left(&3), Some(&4));
+
+        match map.right_entry(4) {
+            RightEntry::Occupied(entry) => {
+                assert_eq!(entry.right(), &4);
+                assert_eq!(entry.get(), &3);
+                assert_eq!(entry.remove(), (4, 3));
+            }
+            RightEntry::Vacant(_) => panic!("expected an occupied entry"),
+        }
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get_by_left(&3), None);
+    }
+}
+
+#[cfg(feature = "test-hash")]
+mod test_hash {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::BuildHasherDefault;
+
+    use two_way_map::{HashTwoWayMap, Overwritten};
+
+    // Hashable but deliberately not ordered
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    struct Uuid(u64, u64);
+
+    #[test]
+    fn test_insert_get() {
+        let mut map: HashTwoWayMap<Uuid, i32> = HashTwoWayMap::default();
+        assert_eq!(map.insert(Uuid(1, 2), 10), Overwritten::Neither);
+        assert_eq!(map.insert(Uuid(3, 4), 20), Overwritten::Neither);
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get_by_left(&Uuid(1, 2)), Some(&10));
+        assert_eq!(map.get_by_right(&20), Some(&Uuid(3, 4)));
+        assert_eq!(map.get_by_left(&Uuid(5, 6)), None);
+        assert!(map.contains_left(&Uuid(3, 4)));
+        assert!(!map.contains_right(&30));
+
+        assert_eq!(
+            map.insert(Uuid(1, 2), 20),
+            Overwritten::Both((Uuid(1, 2), 10), (Uuid(3, 4), 20))
+        );
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_insert_no_overwrite() {
+        let mut map: HashTwoWayMap<i32, i32> = HashTwoWayMap::default();
+        map.insert(1, 2);
+
+        assert_eq!(map.insert_no_overwrite(1, 3), Err((1, 3)));
+        assert_eq!(map.insert_no_overwrite(3, 2), Err((3, 2)));
+        assert_eq!(map.insert_no_overwrite(3, 4), Ok(()));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn test_str_lookups() {
+        let mut map = HashTwoWayMap::<String, String>::default();
+        map.insert(String::from("hello"), String::from("world"));
+        map.insert(String::from("foo"), String::from("bar"));
+
+        assert_eq!(map.get_by_left("hello"), Some(&String::from("world")));
+        assert_eq!(map.get_by_right("bar"), Some(&String::from("foo")));
+        assert!(map.contains_left("foo"));
+        assert!(!map.contains_right("baz"));
+
+        assert_eq!(
+            map.remove_by_left("hello"),
+            Some((String::from("hello"), String::from("world")))
+        );
+        assert_eq!(
+            map.remove_by_right("bar"),
+            Some((String::from("bar"), String::from("foo")))
+        );
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut map: HashTwoWayMap<i32, i32> = (0..10).map(|i| (i, i * 10)).collect();
+
+        map.retain(|left, _| left % 2 == 0);
+
+        assert_eq!(map.len(), 5);
+        assert_eq!(map.get_by_left(&4), Some(&40));
+        assert_eq!(map.get_by_right(&30), None);
+        assert_eq!(map.remove_by_right(&80), Some((80, 8)));
+    }
+
+    #[test]
+    fn test_iterators() {
+        let map: HashTwoWayMap<i32, String> = (1..=3).map(|i| (i, i.to_string())).collect();
+
+        let mut pairs: Vec<_> = map.pairs().collect();
+        pairs.sort();
+        assert_eq!(
+            pairs,
+            vec![
+                (&1, &String::from("1")),
+                (&2, &String::from("2")),
+                (&3, &String::from("3"))
+            ]
+        );
+
+        let mut left_values: Vec<_> = map.left_values().copied().collect();
+        left_values.sort();
+        assert_eq!(left_values, vec![1, 2, 3]);
+
+        let mut right_values: Vec<_> = map.right_values().cloned().collect();
+        right_values.sort();
+        assert_eq!(right_values, vec!["1", "2", "3"]);
+
+        assert_eq!((&map).into_iter().count(), 3);
+
+        let mut owned: Vec<_> = map.clone().into_iter().collect();
+        owned.sort();
+        assert_eq!(owned[0], (1, String::from("1")));
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn test_custom_hasher() {
+        type Map = HashTwoWayMap<&'static str, u32, BuildHasherDefault<DefaultHasher>>;
+
+        let mut map = Map::default();
+        map.insert("one", 1);
+        map.insert("two", 2);
+        assert_eq!(map.get_by_left("two"), Some(&2));
+
+        let mut map = HashTwoWayMap::with_hasher(BuildHasherDefault::<DefaultHasher>::default());
+        map.insert("three", 3);
+        assert_eq!(map.get_by_right(&3), Some(&"three"));
+
+        let map2 = map.clone();
+        assert_eq!(map2.get_by_left("three"), Some(&3));
+    }
+}
+
+#[cfg(feature = "test-mixed-sides")]
+mod test_mixed_sides {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::BuildHasherDefault;
+
+    use two_way_map::{Hashed, Ordered, TwoWayMap};
+
+    type SymbolTable = TwoWayMap<u32, String, Ordered, Hashed>;
+
+    fn symbols() -> SymbolTable {
+        let mut map = SymbolTable::default();
+        map.insert(3, String::from("main"));
+        map.insert(1, String::from("alloc"));
+        map.insert(7, String::from("free"));
+        map.insert(5, String::from("print"));
+        map
+    }
+
+    #[test]
+    fn test_ordered_left_hashed_right() {
+        let mut map = symbols();
+
+        assert_eq!(map.get_by_right("main"), Some(&3));
+        assert_eq!(map.get_by_left(&7), Some(&String::from("free")));
+        assert_eq!(
+            map.left_values().copied().collect::<Vec<_>>(),
+            vec![1, 3, 5, 7]
+        );
+
+        let range: Vec<_> = map.left_range(2..=5).map(|(id, _)| *id).collect();
+        assert_eq!(range, vec![3, 5]);
+
+        assert_eq!(
+            map.remove_by_right("alloc"),
+            Some((String::from("alloc"), 1))
+        );
+        assert_eq!(map.left_range(..).count(), 3);
+    }
+
+    #[test]
+    fn test_hashed_left_ordered_right() {
+        let map: TwoWayMap<String, u32, Hashed, Ordered> =
+            symbols().into_iter().map(|(id, name)| (name, id)).collect();
+
+        assert_eq!(map.get_by_left("print"), Some(&5));
+        let range: Vec<_> = map
+            .right_range(4..)
+            .map(|(_, name)| name.as_str())
+            .collect();
+        assert_eq!(range, vec!["print", "free"]);
+        assert_eq!(
+            map.right_values().copied().collect::<Vec<_>>(),
+            vec![1, 3, 5, 7]
+        );
+    }
+
+    #[test]
+    fn test_with_side_hasher() {
+        let mut map = TwoWayMap::with_right_hasher(BuildHasherDefault::<DefaultHasher>::default());
+        map.insert(2, "two");
+        map.insert(1, "one");
+        assert_eq!(map.get_by_right("two"), Some(&2));
+        assert_eq!(map.left_range(..2).count(), 1);
+
+        let mut map = TwoWayMap::with_left_hasher(BuildHasherDefault::<DefaultHasher>::default());
+        map.insert("two", 2);
+        map.insert("one", 1);
+        assert_eq!(map.get_by_left("one"), Some(&1));
+        assert_eq!(map.right_range(2..).count(), 1);
+
+        let cloned = map.clone();
+        assert_eq!(cloned.get_by_right(&2), Some(&"two"));
+    }
+
+    #[test]
+    fn test_entry_and_retain() {
+        let mut map = symbols();
+
+        assert_eq!(map.right_entry(String::from("main")).or_insert(100), Ok(&3));
+        assert_eq!(
+            map.left_entry(9).or_insert(String::from("exit")),
+            Ok(&String::from("exit"))
+        );
+
+        map.retain(|id, _| *id > 3);
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get_by_right("exit"), Some(&9));
+        assert_eq!(map.get_by_right("main"), None);
+    }
+}
+
+#[cfg(feature = "test-serde")]
+mod test_serde {
+    use two_way_map::{HashTwoWayMap, TwoWayMap};
+
+    #[test]
+    fn test_round_trip() {
+        let mut map = TwoWayMap::new();
+        map.insert(1, "one".to_string());
+        map.insert(2, "two".to_string());
+
+        let json = serde_json::to_string(&map).unwrap();
+        assert_eq!(json, r#"[[1,"one"],[2,"two"]]"#);
+
+        let back: TwoWayMap<i32, String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.len(), 2);
+        assert_eq!(back.get_by_left(&1), Some(&"one".to_string()));
+        assert_eq!(back.get_by_right("two"), Some(&2));
+    }
+
+    #[test]
+    fn test_round_trip_hashed() {
+        let mut map: HashTwoWayMap<String, i32> = HashTwoWayMap::default();
+        map.insert("a".to_string(), 1);
+        map.insert("b".to_string(), 2);
+
+        let json = serde_json::to_string(&map).unwrap();
+        let back: HashTwoWayMap<String, i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.len(), 2);
+        assert_eq!(back.get_by_right(&2), Some(&"b".to_string()));
+    }
+
+    #[test]
+    fn test_repeated_left_is_rejected() {
+        let result: Result<TwoWayMap<i32, i32>, _> = serde_json::from_str("[[1,10],[2,20],[1,30]]");
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("left value of pair 2"), "{err}");
+    }
+
+    #[test]
+    fn test_repeated_right_is_rejected() {
+        let result: Result<TwoWayMap<i32, i32>, _> = serde_json::from_str("[[1,10],[2,10]]");
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("right value of pair 1"), "{err}");
+    }
+
+    fn to_json_map(map: &TwoWayMap<String, u16>) -> String {
+        let mut out = Vec::new();
+        two_way_map::as_map::serialize(map, &mut serde_json::Serializer::new(&mut out)).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn from_json_map(json: &str) -> Result<TwoWayMap<String, u16>, serde_json::Error> {
+        two_way_map::as_map::deserialize(&mut serde_json::Deserializer::from_str(json))
+    }
+
+    #[test]
+    fn test_as_map() {
+        let mut ports = TwoWayMap::new();
+        ports.insert("http".to_string(), 80);
+        ports.insert("https".to_string(), 443);
+
+        let json = to_json_map(&ports);
+        assert_eq!(json, r#"{"http":80,"https":443}"#);
+
+        let back = from_json_map(&json).unwrap();
+        assert_eq!(back.get_by_right(&443), Some(&"https".to_string()));
+    }
+
+    #[test]
+    fn test_as_map_repeated_value_is_rejected() {
+        let err = from_json_map(r#"{"http":80,"www":80}"#)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("right value of pair 1"), "{err}");
+    }
+}
+
+#[cfg(feature = "test-value-mut")]
+mod test_value_mut {
+    use two_way_map::{Overwritten, TwoWayMap};
+
+    #[test]
+    fn test_get_by_left_mut_reindexes() {
+        let mut map = TwoWayMap::new();
+        map.insert(1, "one".to_string());
+        map.insert(2, "two".to_string());
+
+        {
+            let mut right = map.get_by_left_mut(&1).unwrap();
+            assert_eq!(right.left(), &1);
+            right.push_str("!!");
+        }
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get_by_left(&1), Some(&"one!!".to_string()));
+        assert_eq!(map.get_by_right("one!!"), Some(&1));
+        assert_eq!(map.get_by_right("one"), None);
+    }
+
+    #[test]
+    fn test_get_by_right_mut_reindexes() {
+        let mut map = TwoWayMap::new();
+        map.insert(1, 10);
+        map.insert(2, 20);
+
+        *map.get_by_right_mut(&20).unwrap() = 5;
+
+        assert_eq!(map.get_by_left(&5), Some(&20));
+        assert_eq!(map.get_by_left(&2), None);
+        assert_eq!(map.left_values().collect::<Vec<_>>(), vec![&1, &5]);
+    }
+
+    #[test]
+    fn test_missing_value() {
+        let mut map: TwoWayMap<i32, i32> = TwoWayMap::new();
+        assert!(map.get_by_left_mut(&1).is_none());
+        assert!(map.get_by_right_mut(&1).is_none());
+    }
+
+    #[test]
+    fn test_commit_reports_collision() {
+        let mut map = TwoWayMap::new();
+        map.insert(1, 10);
+        map.insert(2, 20);
+
+        let mut right = map.get_by_left_mut(&1).unwrap();
+        *right = 20;
+        assert_eq!(right.commit(), Overwritten::Right(2, 20));
+
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get_by_right(&20), Some(&1));
+
+        let mut left = map.get_by_right_mut(&20).unwrap();
+        *left = 3;
+        assert_eq!(left.commit(), Overwritten::Neither);
+        assert_eq!(map.get_by_left(&3), Some(&20));
+    }
+
+    #[test]
+    fn test_drop_rejects_collision() {
+        let mut map = TwoWayMap::new();
+        map.insert(1, 10);
+        map.insert(2, 20);
+
+        *map.get_by_left_mut(&1).unwrap() = 20;
+        assert_eq!(map.pairs().collect::<Vec<_>>(), vec![(&1, &10), (&2, &20)]);
+
+        {
+            let mut left = map.get_by_right_mut(&20).unwrap();
+            *left = 5;
+            *left = 1;
+        }
+        assert_eq!(map.pairs().collect::<Vec<_>>(), vec![(&1, &10), (&2, &20)]);
+
+        *map.get_by_right_mut(&20).unwrap() = 3;
+        assert_eq!(map.pairs().collect::<Vec<_>>(), vec![(&1, &10), (&3, &20)]);
+    }
+
+    #[test]
+    fn test_unchanged_value() {
+        let mut map = TwoWayMap::new();
+        map.insert(1, 10);
+
+        let right = map.get_by_left_mut(&1).unwrap();
+        assert_eq!(*right, 10);
+        assert_eq!(right.commit(), Overwritten::Neither);
+        assert_eq!(map.get_by_left(&1), Some(&10));
+    }
+}
+
+#[cfg(feature = "test-drain")]
+mod test_drain {
+    use two_way_map::{HashTwoWayMap, TwoWayMap};
+
+    #[test]
+    fn test_drain() {
+        let mut map = TwoWayMap::new();
+        map.insert(2, "two".to_string());
+        map.insert(1, "one".to_string());
+
+        let drained: Vec<_> = map.drain().collect();
+        assert_eq!(
+            drained,
+            vec![(1, "one".to_string()), (2, "two".to_string())]
+        );
+        assert!(map.is_empty());
+
+        map.insert(3, "three".to_string());
+        assert_eq!(map.get_by_right("three"), Some(&3));
+    }
+
+    #[test]
+    fn test_drain_hashed() {
+        let mut map: HashTwoWayMap<i32, i32> = HashTwoWayMap::default();
+        map.insert(1, 10);
+        map.insert(2, 20);
+
+        let mut drained: Vec<_> = map.drain().collect();
+        drained.sort();
+        assert_eq!(drained, vec![(1, 10), (2, 20)]);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_extract_if() {
+        let mut map = TwoWayMap::new();
+        for i in 0..6 {
+            map.insert(i, i * 10);
+        }
+
+        let even = map.extract_if(|left, _| left % 2 == 0);
+        assert_eq!(even, vec![(0, 0), (2, 20), (4, 40)]);
+
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get_by_right(&20), None);
+        assert_eq!(map.get_by_right(&30), Some(&3));
+
+        let mut other: TwoWayMap<i32, i32> = even.into_iter().collect();
+        other.extend(map.extract_if(|_, right| *right > 20));
+        assert_eq!(other.len(), 5);
+        assert_eq!(map.pairs().collect::<Vec<_>>(), vec![(&1, &10)]);
+    }
+
+    #[test]
+    fn test_pop_by_left() {
+        let mut map = TwoWayMap::new();
+        map.insert(2, 'b');
+        map.insert(1, 'c');
+        map.insert(3, 'a');
+
+        assert_eq!(map.pop_first_by_left(), Some((1, 'c')));
+        assert_eq!(map.pop_last_by_left(), Some((3, 'a')));
+        assert_eq!(map.get_by_right(&'a'), None);
+        assert_eq!(map.pop_first_by_left(), Some((2, 'b')));
+        assert_eq!(map.pop_last_by_left(), None);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_pop_by_right() {
+        let mut map = TwoWayMap::new();
+        map.insert(2, 'b');
+        map.insert(1, 'c');
+        map.insert(3, 'a');
+
+        assert_eq!(map.pop_first_by_right(), Some(('a', 3)));
+        assert_eq!(map.pop_last_by_right(), Some(('c', 1)));
+        assert_eq!(map.get_by_left(&1), None);
+        assert_eq!(map.pop_last_by_right(), Some(('b', 2)));
+        assert_eq!(map.pop_first_by_right(), None);
+    }
+}
+
+#[cfg(feature = "test-cursor")]
+mod test_cursor {
+    use std::ops::Bound::{Excluded, Included, Unbounded};
+    use two_way_map::TwoWayMap;
+
+    fn map() -> TwoWayMap<i32, String> {
+        let mut map = TwoWayMap::new();
+        for i in [10, 20, 30, 40] {
+            map.insert(i, format!("e{}", 50 - i));
+        }
+        map
+    }
+
+    #[test]
+    fn test_left_cursor() {
+        let map = map();
+
+        let mut cursor = map.left_cursor_at(Included(&20));
+        assert_eq!(cursor.peek_prev(), Some((&10, &"e40".to_string())));
+        assert_eq!(cursor.peek_next(), Some((&20, &"e30".to_string())));
+
+        assert_eq!(cursor.move_next().map(|(l, _)| *l), Some(20));
+        assert_eq!(cursor.move_next().map(|(l, _)| *l), Some(30));
+        assert_eq!(cursor.move_next().map(|(l, _)| *l), Some(40));
+        assert_eq!(cursor.move_next(), None);
+        assert_eq!(cursor.peek_prev().map(|(l, _)| *l), Some(40));
+
+        assert_eq!(cursor.move_prev().map(|(l, _)| *l), Some(40));
+        assert_eq!(cursor.peek_next().map(|(l, _)| *l), Some(40));
+
+        let cursor = map.left_cursor_at(Excluded(&20));
+        assert_eq!(cursor.peek_next().map(|(l, _)| *l), Some(30));
+
+        // Between two values
+        let cursor = map.left_cursor_at(Included(&25));
+        assert_eq!(cursor.peek_prev().map(|(l, _)| *l), Some(20));
+        assert_eq!(cursor.peek_next().map(|(l, _)| *l), Some(30));
+
+        let mut cursor = map.left_cursor_at::<i32>(Unbounded);
+        assert_eq!(cursor.peek_prev(), None);
+        assert_eq!(cursor.move_prev(), None);
+        assert_eq!(cursor.peek_next().map(|(l, _)| *l), Some(10));
+    }
+
+    #[test]
+    fn test_right_cursor_borrowed() {
+        let map = map();
+
+        let mut cursor = map.right_cursor_at(Included("e20"));
+        assert_eq!(cursor.peek_next(), Some((&"e20".to_string(), &30)));
+        assert_eq!(cursor.peek_prev(), Some((&"e10".to_string(), &40)));
+        cursor.move_next();
+        assert_eq!(cursor.move_next(), Some((&"e30".to_string(), &20)));
+        assert_eq!(cursor.move_prev(), Some((&"e30".to_string(), &20)));
+    }
+
+    #[test]
+    fn test_left_cursor_mut_remove() {
+        let mut map = map();
+
+        let mut cursor = map.left_cursor_mut_at(Included(&20));
+        assert_eq!(cursor.remove_current(), Some((20, "e30".to_string())));
+        assert_eq!(cursor.peek_next().map(|(l, _)| *l), Some(30));
+        assert_eq!(cursor.peek_prev().map(|(l, _)| *l), Some(10));
+
+        assert_eq!(cursor.move_next().map(|(l, _)| *l), Some(30));
+        assert_eq!(cursor.remove_current(), Some((40, "e10".to_string())));
+        assert_eq!(cursor.remove_current(), None);
+        assert_eq!(cursor.move_prev().map(|(l, _)| *l), Some(30));
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get_by_right("e30"), None);
+        assert_eq!(map.get_by_right("e10"), None);
+        assert_eq!(map.right_values().count(), 2);
+
+        // Removing a pair after a cursor has moved away from it
+        assert_eq!(map.remove_by_left(&30), Some((30, "e20".to_string())));
+    }
+
+    #[test]
+    fn test_right_cursor_mut_remove_all() {
+        let mut map = map();
+
+        let mut cursor = map.right_cursor_mut_at::<str>(Unbounded);
+        while let Some((right, left)) = cursor.remove_current() {
+            assert_eq!(right, format!("e{}", 50 - left));
+        }
+        assert!(map.is_empty());
+    }
+}
+
+#[cfg(feature = "test-invariants")]
+mod test_invariants {
+    use std::cell::Cell;
+    use std::cmp::Ordering;
+    use std::mem::ManuallyDrop;
+    use std::ops::Bound::{Included, Unbounded};
+    use std::panic::AssertUnwindSafe;
+    use std::rc::Rc;
+    use two_way_map::{InvariantViolation, TwoWayMap};
+
+    // A value whose ordering can be changed after it was inserted
+    #[derive(Debug)]
+    struct Shifty(Rc<Cell<i32>>);
+
+    impl PartialEq for Shifty {
+        fn eq(&self, other: &Self) -> bool {
+            self.0.get() == other.0.get()
+        }
+    }
+
+    impl Eq for Shifty {}
+
+    impl PartialOrd for Shifty {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Shifty {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.0.get().cmp(&other.0.get())
+        }
+    }
+
+    fn corrupted() -> TwoWayMap<i32, Shifty> {
+        let first = Rc::new(Cell::new(1));
+        let mut map = TwoWayMap::new();
+        map.insert(1, Shifty(first.clone()));
+        map.insert(2, Shifty(Rc::new(Cell::new(2))));
+        assert_eq!(map.check_invariants(), Ok(()));
+
+        // The right index is no longer sorted, so the second pair can't be found in it
+        first.set(3);
+        map
+    }
+
+    #[test]
+    fn test_valid_maps() {
+        let mut map = TwoWayMap::new();
+        assert_eq!(map.check_invariants(), Ok(()));
+        for i in 0..100 {
+            map.insert(i, i * 2);
+        }
+        map.retain(|left, _| left % 3 != 0);
+        assert_eq!(map.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn test_dangling_partner() {
+        let mut map = corrupted();
+        assert_eq!(
+            map.check_invariants(),
+            Err(InvariantViolation::DanglingPartner)
+        );
+
+        assert_eq!(
+            map.try_remove_by_left(&2).map(|pair| pair.is_some()),
+            Err(InvariantViolation::DanglingPartner)
+        );
+        // The pair stays where it was
+        assert_eq!(map.len(), 2);
+        assert!(map.contains_left(&2));
+    }
+
+    #[test]
+    #[should_panic(expected = "missing from one of the indexes")]
+    fn test_remove_panics_with_violation() {
+        let mut map = corrupted();
+        map.remove_by_left(&2);
+    }
+
+    #[test]
+    fn test_pop_panics_with_violation() {
+        let mut map = corrupted();
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| map.pop_last_by_left()));
+        let message = result.unwrap_err().downcast::<String>().unwrap();
+        assert!(
+            message.contains("missing from one of the indexes"),
+            "{message}"
+        );
+
+        // The popped pair was put back
+        assert_eq!(map.len(), 2);
+        assert!(map.contains_left(&2));
+    }
+
+    #[test]
+    fn test_try_pop_and_cursor_remove() {
+        let mut map = corrupted();
+        assert_eq!(
+            map.try_pop_last_by_left().map(|pair| pair.is_some()),
+            Err(InvariantViolation::DanglingPartner)
+        );
+
+        let mut cursor = map.left_cursor_mut_at(Included(&2));
+        assert_eq!(
+            cursor.try_remove_current().map(|pair| pair.is_some()),
+            Err(InvariantViolation::DanglingPartner)
+        );
+        // The cursor didn't move past the pair it couldn't remove
+        assert_eq!(cursor.peek_next().map(|(left, _)| *left), Some(2));
+        assert_eq!(map.len(), 2);
+
+        let mut map: TwoWayMap<i32, i32> = [(1, 10), (2, 20)].into_iter().collect();
+        assert_eq!(map.try_pop_first_by_right(), Ok(Some((10, 1))));
+        let mut cursor = map.right_cursor_mut_at(Included(&20));
+        assert_eq!(cursor.try_remove_current(), Ok(Some((20, 2))));
+        assert_eq!(cursor.try_remove_current(), Ok(None));
+        assert_eq!(map.try_pop_last_by_left(), Ok(None));
+    }
+
+    #[test]
+    fn test_leaked_cursor() {
+        let mut map = TwoWayMap::new();
+        map.insert(1, 10);
+        map.insert(2, 20);
+
+        // A cursor only holds the slot id of the pair after it
+        let _ = ManuallyDrop::new(map.left_cursor_mut_at::<i32>(Unbounded));
+        let _ = ManuallyDrop::new(map.right_cursor_mut_at::<i32>(Unbounded));
+        assert_eq!(map.check_invariants(), Ok(()));
+        assert_eq!(map.try_remove_by_right(&10), Ok(Some((10, 1))));
+
+        map.insert(3, 30);
+        assert_eq!(map.into_iter().collect::<Vec<_>>(), vec![(2, 20), (3, 30)]);
+    }
+
+    #[test]
+    fn test_violation_display() {
+        let violation = InvariantViolation::LengthMismatch { left: 3, right: 2 };
+        assert_eq!(
+            violation.to_string(),
+            "the left index holds 3 pairs but the right index holds 2"
+        );
+    }
+}
+
+#[cfg(feature = "test-set-ops")]
+mod test_set_ops {
+    use two_way_map::{Conflict, ConflictPolicy, PairConflict, Resolution, TwoWayMap};
+
+    fn map(pairs: &[(i32, char)]) -> TwoWayMap<i32, char> {
+        pairs.iter().copied().collect()
+    }
+
+    fn pairs(map: &TwoWayMap<i32, char>) -> Vec<(i32, char)> {
+        map.pairs().map(|(l, r)| (*l, *r)).collect()
+    }
+
+    // 1 and 4 agree, 2 conflicts by left, 'c' by right and 5 with a pair on each side
+    fn sources() -> (TwoWayMap<i32, char>, TwoWayMap<i32, char>) {
+        let mine = map(&[(1, 'a'), (2, 'b'), (3, 'c'), (4, 'd'), (5, 'e'), (6, 'f')]);
+        let theirs = map(&[(1, 'a'), (2, 'x'), (7, 'c'), (4, 'd'), (5, 'f'), (8, 'y')]);
+        (mine, theirs)
+    }
+
+    #[test]
+    fn test_union_keep_self() {
+        let (mine, theirs) = sources();
+        let union = mine.union_with(&theirs, ConflictPolicy::KeepSelf).unwrap();
+        assert_eq!(
+            pairs(&union),
+            [
+                (1, 'a'),
+                (2, 'b'),
+                (3, 'c'),
+                (4, 'd'),
+                (5, 'e'),
+                (6, 'f'),
+                (8, 'y')
+            ]
+        );
+        assert_eq!(union.check_invariants(), Ok(()));
+        assert_eq!(union.get_by_right(&'y'), Some(&8));
+    }
+
+    #[test]
+    fn test_union_keep_other() {
+        let (mine, theirs) = sources();
+        let union = mine.union_with(&theirs, ConflictPolicy::KeepOther).unwrap();
+        assert_eq!(union, theirs);
+        assert_eq!(union.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn test_union_error() {
+        let (mine, theirs) = sources();
+        let error = mine.union_with(&theirs, ConflictPolicy::Error).unwrap_err();
+        assert_eq!(
+            error,
+            PairConflict {
+                other: (2, 'x'),
+                by_left: Some((2, 'b')),
+                by_right: None,
+            }
+        );
+        assert_eq!(error.conflict(), Conflict::Left);
+        assert_eq!(
+            error.to_string(),
+            "pair (2, 'x') of the other map conflicts with (2, 'b')"
+        );
+
+        let disjoint = map(&[(9, 'z')]);
+        assert_eq!(
+            pairs(&mine.union_with(&disjoint, ConflictPolicy::Error).unwrap()).len(),
+            7
+        );
+    }
+
+    #[test]
+    fn test_union_custom() {
+        let (mine, theirs) = sources();
+        let mut seen = Vec::new();
+        let mut resolve = |conflict: PairConflict<&i32, &char>| {
+            seen.push((conflict.conflict(), *conflict.other.0));
+            // Keep the other pair only where it conflicts on both sides
+            match conflict.conflict() {
+                Conflict::Both => Resolution::KeepOther,
+                _ => Resolution::KeepSelf,
+            }
+        };
+        let union = mine
+            .union_with(&theirs, ConflictPolicy::Custom(&mut resolve))
+            .unwrap();
+
+        assert_eq!(
+            seen,
+            [
+                (Conflict::Left, 2),
+                (Conflict::Both, 5),
+                (Conflict::Right, 7)
+            ]
+        );
+        assert_eq!(
+            pairs(&union),
+            [(1, 'a'), (2, 'b'), (3, 'c'), (4, 'd'), (5, 'f'), (8, 'y')]
+        );
+        assert_eq!(union.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn test_intersection_and_difference() {
+        let (mine, theirs) = sources();
+        assert_eq!(pairs(&mine.intersection(&theirs)), [(1, 'a'), (4, 'd')]);
+        assert_eq!(
+            pairs(&mine.difference(&theirs)),
+            [(2, 'b'), (3, 'c'), (5, 'e'), (6, 'f')]
+        );
+        assert_eq!(
+            pairs(&theirs.difference(&mine)),
+            [(2, 'x'), (5, 'f'), (7, 'c'), (8, 'y')]
+        );
+        assert!(mine.intersection(&TwoWayMap::new()).is_empty());
+        assert_eq!(mine.difference(&TwoWayMap::new()), mine);
+    }
+
+    #[test]
+    fn test_symmetric_difference() {
+        let (mine, theirs) = sources();
+        let keep_self = mine
+            .symmetric_difference(&theirs, ConflictPolicy::KeepSelf)
+            .unwrap();
+        assert_eq!(
+            pairs(&keep_self),
+            [(2, 'b'), (3, 'c'), (5, 'e'), (6, 'f'), (8, 'y')]
+        );
+
+        let keep_other = mine
+            .symmetric_difference(&theirs, ConflictPolicy::KeepOther)
+            .unwrap();
+        assert_eq!(pairs(&keep_other), [(2, 'x'), (5, 'f'), (7, 'c'), (8, 'y')]);
+        assert_eq!(keep_other.check_invariants(), Ok(()));
+
+        assert!(
+            mine.symmetric_difference(&theirs, ConflictPolicy::Error)
+                .is_err()
+        );
+        assert!(
+            mine.symmetric_difference(&mine, ConflictPolicy::Error)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    // Compares the merges with the same operations written as loops over `pairs`
+    #[test]
+    fn test_matches_pairwise_loops() {
+        let mut seed = 7u32;
+        let mut next = move || {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 16) % 40
+        };
+        for _ in 0..50 {
+            let mine: TwoWayMap<u32, u32> = (0..30).map(|_| (next(), next())).collect();
+            let theirs: TwoWayMap<u32, u32> = (0..30).map(|_| (next(), next())).collect();
+
+            let mut expected = mine.clone();
+            for (left, right) in &theirs {
+                expected.insert(*left, *right);
+            }
+            let union = mine.union_with(&theirs, ConflictPolicy::KeepOther).unwrap();
+            assert_eq!(union, expected);
+
+            let mut expected = mine.clone();
+            for (left, right) in &theirs {
+                let _ = expected.insert_no_overwrite(*left, *right);
+            }
+            assert_eq!(
+                mine.union_with(&theirs, ConflictPolicy::KeepSelf).unwrap(),
+                expected
+            );
+
+            let in_both = |left: &u32, right: &u32| theirs.get_by_left(left) == Some(right);
+            let mut expected = mine.clone();
+            expected.retain(in_both);
+            let intersection = mine.intersection(&theirs);
+            assert_eq!(intersection, expected);
+
+            let mut expected = mine.clone();
+            expected.retain(|left, right| !in_both(left, right));
+            assert_eq!(mine.difference(&theirs), expected);
+
+            let mut expected = union;
+            expected.retain(|left, _| !intersection.contains_left(left));
+            let symmetric = mine
+                .symmetric_difference(&theirs, ConflictPolicy::KeepOther)
+                .unwrap();
+            assert_eq!(symmetric, expected);
+            assert_eq!(symmetric.check_invariants(), Ok(()));
+        }
+    }
+}
+
+#[cfg(feature = "test-inverse")]
+mod test_inverse {
+    use std::ops::Bound::{Included, Unbounded};
+    use two_way_map::{HashTwoWayMap, Hashed, Ordered, Overwritten, TwoWayMap};
+
+    fn sample() -> TwoWayMap<i32, &'static str> {
+        [(1, "one"), (2, "two"), (3, "three")].into_iter().collect()
+    }
+
+    #[test]
+    fn test_lookups() {
+        let map = sample();
+        let inverse = map.as_inverse();
+
+        assert_eq!(inverse.len(), 3);
+        assert_eq!(inverse.get_by_left("two"), Some(&2));
+        assert_eq!(inverse.get_by_right(&3), Some(&"three"));
+        assert!(inverse.contains_left("one"));
+        assert!(!inverse.contains_right(&4));
+
+        // In the order of the view's left values
+        assert_eq!(
+            inverse.pairs().collect::<Vec<_>>(),
+            [(&"one", &1), (&"three", &3), (&"two", &2)]
+        );
+        assert_eq!(
+            inverse.pairs_by_right().rev().collect::<Vec<_>>(),
+            [(&"three", &3), (&"two", &2), (&"one", &1)]
+        );
+        assert_eq!(
+            inverse.left_values().copied().collect::<Vec<_>>(),
+            ["one", "three", "two"]
+        );
+        assert_eq!(inverse.right_values().len(), 3);
+        assert_eq!(inverse.into_iter().count(), 3);
+
+        assert_eq!(inverse.as_inverse().get_by_left(&1), Some(&"one"));
+    }
+
+    #[test]
+    fn test_ordered_queries() {
+        let map = sample();
+        let inverse = map.as_inverse();
+
+        assert_eq!(inverse.first_by_left(), Some((&"one", &1)));
+        assert_eq!(inverse.last_by_right(), Some((&"three", &3)));
+        assert_eq!(inverse.floor_left("tz"), Some((&"two", &2)));
+        assert_eq!(inverse.higher_right(&1), Some((&"two", &2)));
+        assert_eq!(
+            inverse.left_range("p".."u").collect::<Vec<_>>(),
+            [(&"three", &3), (&"two", &2)]
+        );
+        assert_eq!(
+            inverse.right_range(2..).collect::<Vec<_>>(),
+            [(&2, &"two"), (&3, &"three")]
+        );
+
+        let mut cursor = inverse.left_cursor_at(Included(&"three"));
+        assert_eq!(cursor.move_next(), Some((&"three", &3)));
+        assert_eq!(cursor.peek_next(), Some((&"two", &2)));
+    }
+
+    #[test]
+    fn test_mutation() {
+        let mut map = sample();
+        let inverse = map.as_inverse_mut();
+
+        assert_eq!(inverse.insert("four", 4), Overwritten::Neither);
+        assert_eq!(inverse.insert("uno", 1), Overwritten::Right("one", 1));
+        assert_eq!(
+            inverse.insert("two", 3),
+            Overwritten::Both(("two", 2), ("three", 3))
+        );
+        assert_eq!(inverse.insert_no_overwrite("four", 5), Err(("four", 5)));
+
+        assert_eq!(inverse.remove_by_left("four"), Some(("four", 4)));
+        assert_eq!(inverse.remove_by_right(&1), Some((1, "uno")));
+        assert_eq!(inverse.replace_left("two", "deux"), Ok("two"));
+        assert_eq!(inverse.pop_first_by_left(), Some(("deux", 3)));
+        assert!(inverse.is_empty());
+
+        inverse.insert("a", 1);
+        inverse.insert("b", 2);
+        inverse.insert("c", 3);
+        *inverse.get_by_left_mut("b").unwrap() = 20;
+        inverse.retain(|left, _| *left != "a");
+        assert_eq!(inverse.extract_if(|_, right| *right > 10), [("b", 20)]);
+        assert_eq!(inverse.left_entry("d").or_insert(4), Ok(&4));
+        let mut cursor = inverse.left_cursor_mut_at::<str>(Unbounded);
+        assert_eq!(cursor.remove_current(), Some(("c", 3)));
+        assert_eq!(inverse.check_invariants(), Ok(()));
+
+        // The map sees every change
+        assert_eq!(map.pairs().collect::<Vec<_>>(), [(&4, &"d")]);
+    }
+
+    #[test]
+    fn test_hashed_sides() {
+        let mut map: HashTwoWayMap<i32, String> = HashTwoWayMap::default();
+        map.insert(1, String::from("one"));
+        let inverse = map.as_inverse_mut();
+        inverse.insert(String::from("two"), 2);
+        assert_eq!(inverse.get_by_left("two"), Some(&2));
+        assert_eq!(map.get_by_left(&2).map(String::as_str), Some("two"));
+    }
+
+    #[test]
+    fn test_into_inverse() {
+        let map: TwoWayMap<i32, String> = (0..100).map(|i| (i, format!("{:03}", 99 - i))).collect();
+        let inverse = map.clone().into_inverse();
+
+        assert_eq!(inverse.check_invariants(), Ok(()));
+        assert_eq!(inverse.len(), 100);
+        assert_eq!(inverse.get_by_left("000"), Some(&99));
+        assert_eq!(inverse.get_by_right(&0).map(String::as_str), Some("099"));
+        assert!(
+            inverse
+                .pairs()
+                .map(|(_, l)| l)
+                .eq(map.pairs().map(|(l, _)| l).rev())
+        );
+        assert_eq!(inverse.into_inverse(), map);
+
+        // The sides swap along with the values
+        let map: TwoWayMap<i32, &str, Hashed, Ordered> = [(1, "b"), (2, "a")].into_iter().collect();
+        let inverse: TwoWayMap<&str, i32, Ordered, Hashed> = map.into_inverse();
+        assert_eq!(inverse.check_invariants(), Ok(()));
+        assert_eq!(
+            inverse.pairs().collect::<Vec<_>>(),
+            [(&"a", &2), (&"b", &1)]
+        );
+        assert_eq!(inverse.get_by_right(&1), Some(&"b"));
+    }
+}
+
+#[cfg(feature = "test-compose")]
+mod test_compose {
+    use two_way_map::{HashTwoWayMap, Joined, TwoWayMap};
+
+    // External ids to internal ids, and internal ids to shard keys
+    fn chain() -> (TwoWayMap<&'static str, u32>, TwoWayMap<u32, char>) {
+        let ids = [("a", 1), ("b", 2), ("c", 4)].into_iter().collect();
+        let shards = [(1, 'x'), (3, 'y'), (4, 'z')].into_iter().collect();
+        (ids, shards)
+    }
+
+    #[test]
+    fn test_compose() {
+        let (ids, shards) = chain();
+        let composed = ids.compose(&shards);
+        assert_eq!(composed.check_invariants(), Ok(()));
+        assert_eq!(
+            composed.pairs().collect::<Vec<_>>(),
+            [(&"a", &'x'), (&"c", &'z')]
+        );
+        assert_eq!(composed.get_by_right(&'z'), Some(&"c"));
+
+        assert!(ids.compose(&TwoWayMap::<u32, char>::new()).is_empty());
+    }
+
+    #[test]
+    fn test_join() {
+        let (ids, shards) = chain();
+        let joined: Vec<_> = ids.join(&shards).collect();
+        assert_eq!(
+            joined,
+            [
+                Joined::Both(&"a", &1, &'x'),
+                Joined::First(&"b", &2),
+                Joined::Second(&3, &'y'),
+                Joined::Both(&"c", &4, &'z'),
+            ]
+        );
+        assert_eq!(ids.compose_view(&shards).join().count(), 4);
+    }
+
+    #[test]
+    fn test_composed_view() {
+        let (mut ids, shards) = chain();
+        let view = ids.compose_view(&shards);
+        assert_eq!(view.get_by_left("a"), Some(&'x'));
+        assert_eq!(view.get_by_left("b"), None);
+        assert_eq!(view.get_by_right(&'z'), Some(&"c"));
+        assert_eq!(view.get_by_right(&'y'), None);
+        assert!(view.contains_left("c"));
+        assert!(!view.contains_right(&'q'));
+        assert_eq!(
+            view.pairs().collect::<Vec<_>>(),
+            [(&"a", &'x'), (&"c", &'z')]
+        );
+
+        // The view doesn't copy anything, so it sees the maps as they are when it's made
+        ids.insert("b", 3);
+        let view = ids.compose_view(&shards);
+        assert_eq!(view.get_by_left("b"), Some(&'y'));
+        assert_eq!(view.pairs().count(), 3);
+    }
+
+    #[test]
+    fn test_hashed_sides() {
+        let ids: HashTwoWayMap<String, u32> = [("a".to_string(), 1), ("b".to_string(), 2)]
+            .into_iter()
+            .collect();
+        let shards: HashTwoWayMap<u32, String> = [(2, "z".to_string())].into_iter().collect();
+        let view = ids.compose_view(&shards);
+        assert_eq!(view.get_by_left("b").map(String::as_str), Some("z"));
+        assert_eq!(view.get_by_right("z").map(String::as_str), Some("b"));
+        assert_eq!(view.get_by_left("a"), None);
+    }
+}
+
+#[cfg(feature = "test-bi-multi-map")]
+mod test_bi_multi_map {
+    use std::collections::BTreeSet;
+    use std::ops::Bound::{Excluded, Included, Unbounded};
+    use two_way_map::BiMultiMap;
+
+    // Tags and the documents they're on
+    fn sample() -> BiMultiMap<&'static str, u32> {
+        [("rust", 1), ("rust", 2), ("db", 2), ("db", 3), ("web", 1)]
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_lookups() {
+        let mut map = sample();
+        assert_eq!(map.len(), 5);
+        assert_eq!(map.left_len(), 3);
+        assert_eq!(map.right_len(), 3);
+        assert!(!map.insert("rust", 1));
+        assert_eq!(map.len(), 5);
+
+        assert_eq!(map.get_by_left("rust").copied().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(
+            map.get_by_right(&1).copied().collect::<Vec<_>>(),
+            ["rust", "web"]
+        );
+        assert_eq!(map.get_by_left("go").count(), 0);
+        assert_eq!(map.count_by_left("db"), 2);
+        assert_eq!(map.count_by_right(&3), 1);
+        assert_eq!(map.count_by_right(&4), 0);
+        assert!(map.contains_pair("db", &3));
+        assert!(!map.contains_pair("db", &1));
+        assert!(map.contains_left("web"));
+        assert!(!map.contains_right(&4));
+    }
+
+    #[test]
+    fn test_iteration_and_ranges() {
+        let map = sample();
+        assert_eq!(
+            map.pairs().collect::<Vec<_>>(),
+            [
+                (&"db", &2),
+                (&"db", &3),
+                (&"rust", &1),
+                (&"rust", &2),
+                (&"web", &1)
+            ]
+        );
+        assert_eq!(
+            map.pairs_by_right().rev().collect::<Vec<_>>(),
+            [
+                (&"db", &3),
+                (&"rust", &2),
+                (&"db", &2),
+                (&"web", &1),
+                (&"rust", &1)
+            ]
+        );
+        assert_eq!((&map).into_iter().count(), 5);
+
+        assert_eq!(
+            map.left_range::<str>((Excluded("db"), Unbounded))
+                .collect::<Vec<_>>(),
+            [(&"rust", &1), (&"rust", &2), (&"web", &1)]
+        );
+        assert_eq!(
+            map.right_range(2..).collect::<Vec<_>>(),
+            [(&2, &"db"), (&2, &"rust"), (&3, &"db")]
+        );
+        assert_eq!(map.right_range(..=1).next_back(), Some((&1, &"web")));
+        assert_eq!(map.left_range::<str>((Included("x"), Unbounded)).count(), 0);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut map = sample();
+
+        // The first pair inserted for "rust" and for 1, so both groups get a new key
+        assert_eq!(map.remove_pair("rust", &1), Some(("rust", 1)));
+        assert_eq!(map.remove_pair("rust", &1), None);
+        assert_eq!(map.remove_pair("go", &1), None);
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.get_by_left("rust").copied().collect::<Vec<_>>(), [2]);
+        assert_eq!(map.get_by_right(&1).copied().collect::<Vec<_>>(), ["web"]);
+
+        // The last pair of "web" and of 1
+        assert_eq!(map.remove_pair("web", &1), Some(("web", 1)));
+        assert!(!map.contains_left("web"));
+        assert!(!map.contains_right(&1));
+        assert_eq!(map.left_len(), 2);
+
+        assert_eq!(map.remove_all_by_right(&2), [(2, "db"), (2, "rust")]);
+        assert_eq!(map.remove_all_by_right(&2), []);
+        assert!(!map.contains_left("rust"));
+        assert_eq!(map.remove_all_by_left("db"), [("db", 3)]);
+        assert!(map.is_empty());
+        assert_eq!(map.right_len(), 0);
+
+        let mut map = sample();
+        assert_eq!(map.remove_all_by_left("rust"), [("rust", 1), ("rust", 2)]);
+        assert_eq!(map.get_by_right(&1).copied().collect::<Vec<_>>(), ["web"]);
+        assert_eq!(map.get_by_right(&2).copied().collect::<Vec<_>>(), ["db"]);
+        assert!(map.insert("rust", 1));
+        assert_eq!(map.count_by_right(&1), 2);
+    }
+
+    #[test]
+    fn test_borrowed_lookups() {
+        let mut map: BiMultiMap<String, String> = BiMultiMap::new();
+        map.insert("tag".to_string(), "doc".to_string());
+        map.insert("tag".to_string(), "page".to_string());
+        assert_eq!(map.count_by_left("tag"), 2);
+        assert!(map.contains_pair("tag", "page"));
+        assert_eq!(
+            map.remove_pair("tag", "doc"),
+            Some(("tag".to_string(), "doc".to_string()))
+        );
+        assert_eq!(map.get_by_right("page").collect::<Vec<_>>(), ["tag"]);
+    }
+
+    #[test]
+    fn test_traits() {
+        let map = sample();
+        let copy = map.clone();
+        assert_eq!(copy, map);
+        assert_eq!(copy.get_by_left("db").count(), 2);
+
+        let reversed: BiMultiMap<_, _> = map.pairs().rev().map(|(l, r)| (*l, *r)).collect();
+        assert_eq!(reversed, map);
+
+        let mut other = map.clone();
+        other.remove_pair("db", &3);
+        assert_ne!(other, map);
+        other.extend([("db", 3)]);
+        assert_eq!(other, map);
+
+        let small: BiMultiMap<i32, i32> = [(1, 2), (1, 3)].into_iter().collect();
+        assert_eq!(format!("{small:?}"), "{(1, 2), (1, 3)}");
+        assert_eq!(BiMultiMap::<i32, i32>::default().len(), 0);
+    }
+
+    // Runs random inserts and removals against a set of pairs
+    #[test]
+    fn test_matches_set_of_pairs() {
+        let mut seed = 11u32;
+        let mut next = move || {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 16) % 12
+        };
+        let mut map = BiMultiMap::new();
+        let mut expected = BTreeSet::new();
+        for _ in 0..2000 {
+            let (left, right) = (next(), next());
+            match next() % 6 {
+                0..=2 => assert_eq!(map.insert(left, right), expected.insert((left, right))),
+                3 => assert_eq!(
+                    map.remove_pair(&left, &right).is_some(),
+                    expected.remove(&(left, right))
+                ),
+                4 => {
+                    let removed = map.remove_all_by_left(&left);
+                    assert!(removed.iter().all(|pair| expected.remove(pair)));
+                    assert!(expected.iter().all(|(l, _)| *l != left));
+                }
+                _ => {
+                    let removed = map.remove_all_by_right(&right);
+                    assert!(removed.iter().all(|(r, l)| expected.remove(&(*l, *r))));
+                    assert!(expected.iter().all(|(_, r)| *r != right));
+                }
+            }
+
+            assert_eq!(map.len(), expected.len());
+            let pairs: Vec<_> = map.pairs().map(|(l, r)| (*l, *r)).collect();
+            assert!(pairs.iter().eq(expected.iter()));
+            let by_right: Vec<_> = map.pairs_by_right().map(|(l, r)| (*r, *l)).collect();
+            assert!(by_right.is_sorted());
+            let by_right: BTreeSet<_> = by_right.into_iter().map(|(r, l)| (l, r)).collect();
+            assert_eq!(by_right, expected);
+            assert_eq!(
+                map.count_by_left(&left),
+                expected.iter().filter(|(l, _)| *l == left).count()
+            );
+        }
+    }
+}
+
+#[cfg(feature = "test-indexed-multi-map")]
+mod test_indexed_multi_map {
+    use std::ops::Bound::{Excluded, Unbounded};
+    use two_way_map::IndexedMultiMap;
+
+    // Directories and the files in them
+    fn sample() -> IndexedMultiMap<&'static str, u32> {
+        let mut map = IndexedMultiMap::new();
+        for (dir, file) in [
+            ("src", 3),
+            ("src", 1),
+            ("docs", 2),
+            ("src", 5),
+            ("tests", 4),
+        ] {
+            map.insert(dir, file).unwrap();
+        }
+        map
+    }
+
+    #[test]
+    fn test_insert_and_lookups() {
+        let mut map = sample();
+        assert_eq!(map.len(), 5);
+        assert_eq!(map.left_len(), 3);
+
+        // Every right value has a single owner
+        assert_eq!(map.insert("docs", 1), Err(("docs", 1)));
+        assert_eq!(map.insert("src", 1), Err(("src", 1)));
+        assert_eq!(map.len(), 5);
+
+        assert_eq!(
+            map.get_by_left("src").copied().collect::<Vec<_>>(),
+            [1, 3, 5]
+        );
+        assert_eq!(map.get_by_left("none").count(), 0);
+        assert_eq!(map.get_by_right(&4), Some(&"tests"));
+        assert_eq!(map.get_by_right(&9), None);
+        assert_eq!(map.count_by_left("src"), 3);
+        assert!(map.contains_left("docs"));
+        assert!(!map.contains_right(&0));
+    }
+
+    #[test]
+    fn test_ordered_iteration() {
+        let map = sample();
+        assert_eq!(
+            map.pairs().collect::<Vec<_>>(),
+            [
+                (&"docs", &2),
+                (&"src", &1),
+                (&"src", &3),
+                (&"src", &5),
+                (&"tests", &4)
+            ]
+        );
+        assert_eq!(
+            map.pairs_by_right().map(|(_, r)| *r).collect::<Vec<_>>(),
+            [1, 2, 3, 4, 5]
+        );
+        assert_eq!(
+            map.left_range::<str>((Excluded("docs"), Unbounded))
+                .rev()
+                .collect::<Vec<_>>(),
+            [(&"tests", &4), (&"src", &5), (&"src", &3), (&"src", &1)]
+        );
+        assert_eq!(
+            map.right_range(2..4).collect::<Vec<_>>(),
+            [(&2, &"docs"), (&3, &"src")]
+        );
+        assert_eq!((&map).into_iter().count(), 5);
+    }
+
+    #[test]
+    fn test_reparent() {
+        let mut map = sample();
+        assert_eq!(map.reparent(&3, "docs"), Ok("src"));
+        assert_eq!(map.get_by_right(&3), Some(&"docs"));
+        assert_eq!(map.get_by_left("docs").copied().collect::<Vec<_>>(), [2, 3]);
+        assert_eq!(map.get_by_left("src").copied().collect::<Vec<_>>(), [1, 5]);
+
+        // The last right value of "tests", which goes away with it
+        assert_eq!(map.reparent(&4, "new"), Ok("tests"));
+        assert!(!map.contains_left("tests"));
+        assert_eq!(map.left_len(), 3);
+
+        assert_eq!(map.reparent(&1, "src"), Ok("src"));
+        assert_eq!(map.reparent(&9, "src"), Err("src"));
+        assert_eq!(map.len(), 5);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut map = sample();
+        assert_eq!(map.remove_by_right(&3), Some((3, "src")));
+        assert_eq!(map.remove_by_right(&3), None);
+        assert_eq!(map.get_by_left("src").copied().collect::<Vec<_>>(), [1, 5]);
+
+        assert_eq!(map.remove_left_cascade("src"), [("src", 1), ("src", 5)]);
+        assert_eq!(map.remove_left_cascade("src"), []);
+        assert!(!map.contains_right(&1));
+        assert!(!map.contains_right(&5));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.left_len(), 2);
+
+        map.insert("src", 1).unwrap();
+        assert_eq!(map.get_by_right(&1), Some(&"src"));
+        map.clear();
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_traits() {
+        let map = sample();
+        assert_eq!(map.clone(), map);
+
+        // A right value that comes again is moved
+        let collected: IndexedMultiMap<_, _> = [("a", 1), ("b", 2), ("b", 1)].into_iter().collect();
+        assert_eq!(collected.get_by_right(&1), Some(&"b"));
+        assert!(!collected.contains_left("a"));
+        assert_eq!(format!("{collected:?}"), r#"{("b", 1), ("b", 2)}"#);
+
+        let mut other = IndexedMultiMap::default();
+        other.extend(map.pairs().map(|(l, r)| (*l, *r)));
+        assert_eq!(other, map);
+        other.reparent(&4, "src").unwrap();
+        assert_ne!(other, map);
+    }
+}
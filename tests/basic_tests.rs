@@ -243,6 +243,106 @@ fn test_insert_no_overwrite_string() {
     assert_eq!(map.len(), 2);
 }
 
+#[cfg(feature = "test-insert")]
+#[test]
+fn test_insert_overwritten() {
+    use two_way_map::Overwritten;
+
+    let mut map = TwoWayMap::new();
+    assert_eq!(map.insert(1, 2), Overwritten::Neither);
+    assert_eq!(map.insert(3, 4), Overwritten::Neither);
+
+    assert_eq!(map.insert(1, 2), Overwritten::Pair(1, 2));
+    assert_eq!(map.len(), 2);
+
+    assert_eq!(map.insert(1, 5), Overwritten::Left(1, 2));
+    assert_eq!(map.get_by_right(&2), None);
+    assert_eq!(map.len(), 2);
+
+    assert_eq!(map.insert(6, 4), Overwritten::Right(3, 4));
+    assert_eq!(map.get_by_left(&3), None);
+    assert_eq!(map.len(), 2);
+
+    assert_eq!(map.insert(1, 4), Overwritten::Both((1, 5), (6, 4)));
+    assert_eq!(map.len(), 1);
+    assert_eq!(map.get_by_left(&1), Some(&4));
+    assert_eq!(map.get_by_right(&4), Some(&1));
+}
+
+#[cfg(feature = "test-insert")]
+#[test]
+fn test_insert_overwritten_string() {
+    use two_way_map::Overwritten;
+
+    let mut map = TwoWayMap::<String, String>::new();
+    assert!(
+        !map.insert(String::from("hello"), String::from("world"))
+            .did_overwrite()
+    );
+    assert_eq!(
+        map.insert(String::from("hello"), String::from("there")),
+        Overwritten::Left(String::from("hello"), String::from("world"))
+    );
+    assert_eq!(
+        map.insert(String::from("hi"), String::from("there")),
+        Overwritten::Right(String::from("hello"), String::from("there"))
+    );
+    assert_eq!(map.len(), 1);
+}
+
+#[cfg(feature = "test-insert")]
+#[test]
+fn test_replace_left() {
+    use two_way_map::ReplaceError;
+
+    let mut map = TwoWayMap::new();
+    map.insert(1, 10);
+    map.insert(2, 20);
+
+    assert_eq!(map.replace_left(&1, 5), Ok(1));
+    assert_eq!(map.get_by_left(&5), Some(&10));
+    assert_eq!(map.get_by_right(&10), Some(&5));
+    assert!(!map.contains_left(&1));
+    assert_eq!(map.len(), 2);
+
+    assert_eq!(map.replace_left(&5, 2), Err(ReplaceError::AlreadyMapped(2)));
+    assert_eq!(map.replace_left(&7, 8), Err(ReplaceError::NotFound(8)));
+    assert_eq!(map.get_by_left(&5), Some(&10));
+    assert_eq!(map.get_by_left(&2), Some(&20));
+
+    // Replacing a value with itself is not a collision
+    assert_eq!(map.replace_left(&2, 2), Ok(2));
+    assert_eq!(map.len(), 2);
+}
+
+#[cfg(feature = "test-insert")]
+#[test]
+fn test_replace_right_string() {
+    use two_way_map::ReplaceError;
+
+    let mut map = TwoWayMap::<i32, String>::new();
+    map.insert(1, String::from("one"));
+    map.insert(2, String::from("two"));
+
+    assert_eq!(
+        map.replace_right("one", String::from("uno")),
+        Ok(String::from("one"))
+    );
+    assert_eq!(map.get_by_left(&1), Some(&String::from("uno")));
+    assert_eq!(map.get_by_right("uno"), Some(&1));
+    assert_eq!(map.get_by_right("one"), None);
+
+    assert_eq!(
+        map.replace_right("uno", String::from("two")),
+        Err(ReplaceError::AlreadyMapped(String::from("two")))
+    );
+    assert_eq!(
+        map.replace_right("three", String::from("tres")),
+        Err(ReplaceError::NotFound(String::from("tres")))
+    );
+    assert_eq!(map.len(), 2);
+}
+
 #[cfg(feature = "test-removal")]
 #[test]
 fn test_remove_by_left() {
@@ -596,6 +696,93 @@ fn test_right_values_string() {
     );
 }
 
+#[cfg(feature = "test-iteration")]
+#[test]
+fn test_iterators_rev_and_len() {
+    let mut map = TwoWayMap::new();
+    map.insert(1, 30);
+    map.insert(2, 20);
+    map.insert(3, 10);
+
+    let pairs = map.pairs();
+    assert_eq!(pairs.len(), 3);
+    assert_eq!(
+        pairs.rev().collect::<Vec<_>>(),
+        vec![(&3, &10), (&2, &20), (&1, &30)]
+    );
+    assert_eq!(map.left_values().next_back(), Some(&3));
+    assert_eq!(map.right_values().next_back(), Some(&30));
+    assert_eq!(map.right_values().len(), 3);
+
+    let mut pairs = map.pairs();
+    pairs.next();
+    assert_eq!(pairs.len(), 2);
+
+    let mut range = map.left_range(2..);
+    assert_eq!(range.next_back(), Some((&3, &10)));
+    assert_eq!(map.right_range(..25).next_back(), Some((&20, &2)));
+
+    let into: Vec<_> = map.clone().into_iter().rev().collect();
+    assert_eq!(into, vec![(3, 10), (2, 20), (1, 30)]);
+    assert_eq!(map.clone().into_iter().len(), 3);
+}
+
+#[cfg(feature = "test-iteration")]
+#[test]
+fn test_pairs_by_right() {
+    let mut map = TwoWayMap::new();
+    map.insert(String::from("carol"), 3);
+    map.insert(String::from("alice"), 2);
+    map.insert(String::from("bob"), 1);
+
+    let names: Vec<_> = map.pairs_by_right().map(|(l, _)| l.as_str()).collect();
+    assert_eq!(names, vec!["bob", "alice", "carol"]);
+    assert_eq!(map.pairs_by_right().len(), 3);
+    assert_eq!(
+        map.pairs_by_right().next_back(),
+        Some((&String::from("carol"), &3))
+    );
+
+    let owned: Vec<_> = map.into_iter_by_right().collect();
+    assert_eq!(
+        owned,
+        vec![
+            (String::from("bob"), 1),
+            (String::from("alice"), 2),
+            (String::from("carol"), 3)
+        ]
+    );
+}
+
+#[cfg(feature = "test-iteration")]
+#[test]
+fn test_iterators_fused_clone_debug() {
+    use two_way_map::{LeftValues, Pairs};
+
+    struct Cursor<'a> {
+        pairs: Pairs<'a, i32, i32>,
+        lefts: LeftValues<'a, i32, i32>,
+    }
+
+    let mut map = TwoWayMap::new();
+    map.insert(1, 2);
+
+    let mut cursor = Cursor {
+        pairs: map.pairs(),
+        lefts: map.left_values(),
+    };
+    let copy = cursor.pairs.clone();
+    assert_eq!(cursor.pairs.next(), Some((&1, &2)));
+    assert_eq!(cursor.pairs.next(), None);
+    assert_eq!(cursor.pairs.next(), None);
+    assert_eq!(copy.collect::<Vec<_>>(), vec![(&1, &2)]);
+    assert_eq!(cursor.lefts.next(), Some(&1));
+
+    assert!(format!("{:?}", map.pairs()).starts_with("Pairs"));
+    assert!(format!("{:?}", map.left_range(..)).starts_with("LeftRange"));
+    assert!(format!("{:?}", map.into_iter()).starts_with("IntoIter"));
+}
+
 #[cfg(feature = "test-range-queries")]
 #[test]
 fn test_range() {
@@ -682,6 +869,96 @@ fn test_left_range_string_one_more() {
     );
 }
 
+#[cfg(feature = "test-range-queries")]
+#[test]
+fn test_range_borrowed_bounds() {
+    use std::ops::Bound::{Excluded, Included, Unbounded};
+
+    let mut map = TwoWayMap::new();
+    map.insert(String::from("apple"), String::from("red"));
+    map.insert(String::from("kiwi"), String::from("green"));
+    map.insert(String::from("plum"), String::from("purple"));
+
+    let range: Vec<_> = map
+        .left_range_borrowed("a".."m")
+        .map(|(l, _)| l.as_str())
+        .collect();
+    assert_eq!(range, vec!["apple", "kiwi"]);
+
+    let range: Vec<_> = map
+        .left_range_borrowed((Included("kiwi"), Excluded("plum")))
+        .map(|(l, _)| l.as_str())
+        .collect();
+    assert_eq!(range, vec!["kiwi"]);
+
+    let range: Vec<_> = map
+        .right_range_borrowed((Excluded("green"), Unbounded))
+        .map(|(r, _)| r.as_str())
+        .collect();
+    assert_eq!(range, vec!["purple", "red"]);
+}
+
+#[cfg(feature = "test-range-queries")]
+#[test]
+fn test_range_rev() {
+    let mut map = TwoWayMap::new();
+    for i in 0..10 {
+        map.insert(i, 100 - i);
+    }
+
+    let range: Vec<_> = map.left_range(2..5).rev().collect();
+    assert_eq!(range, vec![(&4, &96), (&3, &97), (&2, &98)]);
+
+    let mut range = map.right_range(..=92);
+    assert_eq!(range.next(), Some((&91, &9)));
+    assert_eq!(range.next_back(), Some((&92, &8)));
+    assert_eq!(range.next(), None);
+}
+
+#[cfg(feature = "test-range-queries")]
+#[test]
+fn test_nearest_left() {
+    let mut map = TwoWayMap::new();
+    map.insert(100, "a");
+    map.insert(200, "b");
+    map.insert(300, "c");
+
+    assert_eq!(map.first_by_left(), Some((&100, &"a")));
+    assert_eq!(map.last_by_left(), Some((&300, &"c")));
+
+    assert_eq!(map.floor_left(&250), Some((&200, &"b")));
+    assert_eq!(map.floor_left(&200), Some((&200, &"b")));
+    assert_eq!(map.floor_left(&50), None);
+    assert_eq!(map.ceiling_left(&250), Some((&300, &"c")));
+    assert_eq!(map.ceiling_left(&300), Some((&300, &"c")));
+    assert_eq!(map.ceiling_left(&301), None);
+    assert_eq!(map.lower_left(&200), Some((&100, &"a")));
+    assert_eq!(map.lower_left(&100), None);
+    assert_eq!(map.higher_left(&200), Some((&300, &"c")));
+    assert_eq!(map.higher_left(&300), None);
+}
+
+#[cfg(feature = "test-range-queries")]
+#[test]
+fn test_nearest_right_borrowed() {
+    let mut map = TwoWayMap::new();
+    map.insert(1, String::from("b"));
+    map.insert(2, String::from("d"));
+    map.insert(3, String::from("f"));
+
+    assert_eq!(map.first_by_right(), Some((&1, &String::from("b"))));
+    assert_eq!(map.last_by_right(), Some((&3, &String::from("f"))));
+    assert_eq!(map.floor_right("c"), Some((&1, &String::from("b"))));
+    assert_eq!(map.ceiling_right("c"), Some((&2, &String::from("d"))));
+    assert_eq!(map.lower_right("d"), Some((&1, &String::from("b"))));
+    assert_eq!(map.higher_right("d"), Some((&3, &String::from("f"))));
+    assert_eq!(map.floor_right("a"), None);
+
+    let empty = TwoWayMap::<i32, i32>::new();
+    assert_eq!(empty.first_by_left(), None);
+    assert_eq!(empty.last_by_right(), None);
+}
+
 #[cfg(feature = "test-retain")]
 #[test]
 fn test_retain() {
@@ -724,6 +1001,66 @@ fn test_retain_string() {
     );
 }
 
+#[cfg(feature = "test-retain")]
+#[test]
+fn test_retain_calls_predicate_once_per_pair() {
+    let mut map = TwoWayMap::new();
+    for i in 0..10 {
+        map.insert(i, 100 - i);
+    }
+
+    let mut calls = 0;
+    map.retain(|_, _| {
+        calls += 1;
+        true
+    });
+    assert_eq!(calls, 10);
+
+    // A stateful predicate must make one decision per pair, shared by both indexes
+    let mut kept = 0;
+    map.retain(|_, _| {
+        kept += 1;
+        kept <= 3
+    });
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.left_values().collect::<Vec<_>>(), vec![&0, &1, &2]);
+    assert_eq!(map.right_values().collect::<Vec<_>>(), vec![&98, &99, &100]);
+
+    // Every remaining pair can still be removed from either side
+    assert_eq!(map.remove_by_right(&99), Some((99, 1)));
+    assert_eq!(map.remove_by_left(&0), Some((0, 100)));
+    assert_eq!(map.remove_by_right(&98), Some((98, 2)));
+    assert!(map.is_empty());
+}
+
+#[cfg(feature = "test-retain")]
+#[test]
+fn test_retain_by_right() {
+    let mut map = TwoWayMap::new();
+    for i in 0..10 {
+        map.insert(i, 100 - i);
+    }
+
+    let mut visited = Vec::new();
+    map.retain_by_right(|left, right| {
+        visited.push(*right);
+        *left >= 7
+    });
+    assert_eq!(visited, (91..=100).collect::<Vec<_>>());
+
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get_by_left(&7), Some(&93));
+    assert_eq!(map.get_by_right(&97), None);
+
+    let mut kept = 0;
+    map.retain_by_right(|_, _| {
+        kept += 1;
+        kept <= 1
+    });
+    assert_eq!(map.pairs().collect::<Vec<_>>(), vec![(&9, &91)]);
+    assert_eq!(map.remove_by_left(&9), Some((9, 91)));
+}
+
 #[cfg(feature = "test-traits")]
 #[test]
 fn test_clone() {
@@ -829,6 +1166,57 @@ fn test_from_iterator_string() {
     );
 }
 
+#[cfg(feature = "test-from-iterator")]
+#[test]
+fn test_try_from_iter() {
+    use two_way_map::{Conflict, ConflictError};
+
+    let map: TwoWayMap<i32, i32> = TwoWayMap::try_from_iter(vec![(1, 2), (3, 4)]).unwrap();
+    assert_eq!(map.len(), 2);
+
+    let err = TwoWayMap::<i32, i32>::try_from_iter(vec![(1, 2), (3, 4), (1, 5)]).unwrap_err();
+    assert_eq!(
+        err,
+        ConflictError {
+            position: 2,
+            pair: (1, 5),
+            conflict: Conflict::Left,
+        }
+    );
+
+    let err = TwoWayMap::<i32, i32>::try_from_iter(vec![(1, 2), (3, 2)]).unwrap_err();
+    assert_eq!(err.position, 1);
+    assert_eq!(err.conflict, Conflict::Right);
+
+    let err = TwoWayMap::<i32, i32>::try_from_iter(vec![(1, 2), (3, 4), (1, 4)]).unwrap_err();
+    assert_eq!(err.conflict, Conflict::Both);
+    assert_eq!(
+        err.to_string(),
+        "pair (1, 4) at position 2: left and right values are already mapped"
+    );
+}
+
+#[cfg(feature = "test-from-iterator")]
+#[test]
+fn test_try_extend() {
+    use two_way_map::Conflict;
+
+    let mut map = TwoWayMap::new();
+    map.insert(1, 2);
+
+    let err = map.try_extend(vec![(3, 4), (5, 2), (6, 7)]).unwrap_err();
+    assert_eq!(err.position, 1);
+    assert_eq!(err.pair, (5, 2));
+    assert_eq!(err.conflict, Conflict::Right);
+    // The pairs before the conflict were added, the ones after it weren't
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get_by_left(&3), Some(&4));
+    assert!(!map.contains_left(&6));
+
+    assert_eq!(map.try_extend(vec![(6, 7)]), Ok(()));
+    assert_eq!(map.len(), 3);
+}
+
 #[cfg(feature = "test-into-iterator")]
 #[test]
 fn test_into_iter() {
@@ -1013,3 +1401,74 @@ fn test_default_no_default_trait() {
     let map: TwoWayMap<SomeStruct, SomeStruct> = TwoWayMap::default();
     assert_eq!(map.len(), 0);
 }
+
+#[cfg(feature = "test-traits")]
+#[test]
+fn test_eq_ignores_insertion_order() {
+    let map: TwoWayMap<i32, &str> = [(1, "a"), (2, "b"), (3, "c")].into_iter().collect();
+    let mut other = TwoWayMap::new();
+    other.insert(3, "c");
+    other.insert(1, "x");
+    other.insert(2, "b");
+    assert_ne!(map, other);
+
+    other.insert(1, "a");
+    assert_eq!(map, other);
+
+    other.remove_by_left(&3);
+    assert_ne!(map, other);
+}
+
+#[cfg(feature = "test-traits")]
+#[test]
+fn test_eq_hashed_sides() {
+    use two_way_map::HashTwoWayMap;
+
+    let map: HashTwoWayMap<i32, i32> = (0..100).map(|i| (i, i * 2)).collect();
+    let other: HashTwoWayMap<i32, i32> = (0..100).rev().map(|i| (i, i * 2)).collect();
+    assert_eq!(map, other);
+
+    let shifted: HashTwoWayMap<i32, i32> = (0..100).map(|i| (i, i * 2 + 1)).collect();
+    assert_ne!(map, shifted);
+}
+
+#[cfg(feature = "test-traits")]
+#[test]
+fn test_hash() {
+    use std::collections::HashSet;
+    use std::hash::{BuildHasher, RandomState};
+
+    let map: TwoWayMap<i32, i32> = [(1, 10), (2, 20)].into_iter().collect();
+    let other: TwoWayMap<i32, i32> = [(2, 20), (1, 10)].into_iter().collect();
+    let state = RandomState::new();
+    assert_eq!(state.hash_one(&map), state.hash_one(&other));
+
+    let mut maps = HashSet::new();
+    assert!(maps.insert(map));
+    assert!(!maps.insert(other));
+    assert!(maps.insert(TwoWayMap::new()));
+}
+
+#[cfg(feature = "test-traits")]
+#[test]
+fn test_ord() {
+    use std::collections::BTreeSet;
+
+    let map = |pairs: &[(i32, i32)]| pairs.iter().copied().collect::<TwoWayMap<i32, i32>>();
+
+    // Lexicographic over the pairs in left order
+    assert!(map(&[(1, 10), (2, 20)]) < map(&[(1, 10), (3, 0)]));
+    assert!(map(&[(1, 10), (2, 20)]) < map(&[(1, 11)]));
+    assert!(map(&[(1, 10)]) < map(&[(1, 10), (2, 20)]));
+    assert!(map(&[]) < map(&[(0, 0)]));
+    assert_eq!(
+        map(&[(2, 20), (1, 10)]).cmp(&map(&[(1, 10), (2, 20)])),
+        std::cmp::Ordering::Equal
+    );
+
+    let maps: BTreeSet<_> = [map(&[(2, 0)]), map(&[(1, 5)]), map(&[(1, 5)])]
+        .into_iter()
+        .collect();
+    assert_eq!(maps.len(), 2);
+    assert_eq!(maps.first(), Some(&map(&[(1, 5)])));
+}
@@ -0,0 +1,83 @@
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+thread_local! {
+    // How many more comparisons, hashes and clones of a `Bomb` go through before one panics
+    static FUSE: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+// Makes the comparison, hash or clone after the next `n` ones panic
+pub fn arm(n: usize) {
+    FUSE.set(Some(n));
+}
+
+pub fn disarm() {
+    FUSE.set(None);
+}
+
+fn tick() {
+    match FUSE.get() {
+        Some(0) => {
+            // Only once, so that undoing what was done before can compare again
+            disarm();
+            // Unlike `panic!`, this doesn't print a message for every panic caught by a test
+            std::panic::resume_unwind(Box::new("bomb went off"));
+        }
+        Some(n) => FUSE.set(Some(n - 1)),
+        None => {}
+    }
+}
+
+#[derive(Debug)]
+pub struct Bomb(pub i32);
+
+impl PartialEq for Bomb {
+    fn eq(&self, other: &Self) -> bool {
+        tick();
+        self.0 == other.0
+    }
+}
+
+impl Eq for Bomb {}
+
+impl PartialOrd for Bomb {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Bomb {
+    fn cmp(&self, other: &Self) -> Ordering {
+        tick();
+        self.0.cmp(&other.0)
+    }
+}
+
+impl Hash for Bomb {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        tick();
+        self.0.hash(state);
+    }
+}
+
+impl Clone for Bomb {
+    fn clone(&self) -> Self {
+        tick();
+        Bomb(self.0)
+    }
+}
+
+#[test]
+fn test_bomb() {
+    let result = std::panic::catch_unwind(|| {
+        arm(2);
+        let bomb = Bomb(1);
+        assert!(bomb < Bomb(2));
+        assert_eq!(bomb.clone(), bomb);
+    });
+    assert!(result.is_err());
+
+    // It only goes off once
+    assert!(Bomb(1) < Bomb(2));
+}
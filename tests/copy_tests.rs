@@ -10,15 +10,15 @@ fn test_clone_counts() {
     let mut map = TwoWayMap::new();
     map.insert(1, dummy);
     assert_eq!(recorder.borrow().clones, 0);
-    assert_eq!(recorder.borrow().dropped, false);
+    assert!(!recorder.borrow().dropped);
 
     let map2 = map.clone();
     assert_eq!(recorder.borrow().clones, 1);
-    assert_eq!(recorder.borrow().dropped, false);
+    assert!(!recorder.borrow().dropped);
 
     let _map3 = map2.clone();
     assert_eq!(recorder.borrow().clones, 2);
-    assert_eq!(recorder.borrow().dropped, false);
+    assert!(!recorder.borrow().dropped);
 }
 
 #[cfg(feature = "test-basic")]
@@ -31,11 +31,11 @@ fn test_moves() {
     let mut map = TwoWayMap::new();
     map.insert(1, dummy);
     assert_eq!(recorder.borrow().clones, 0);
-    assert_eq!(recorder.borrow().dropped, false);
+    assert!(!recorder.borrow().dropped);
 
     let _map2 = map;
     assert_eq!(recorder.borrow().clones, 0);
-    assert_eq!(recorder.borrow().dropped, false);
+    assert!(!recorder.borrow().dropped);
 }
 
 #[cfg(feature = "test-into-iterator")]
@@ -49,17 +49,17 @@ fn test_into_iter_clone_counts() {
         let mut map = TwoWayMap::new();
         map.insert(1, dummy);
         assert_eq!(recorder.borrow().clones, 0);
-        assert_eq!(recorder.borrow().dropped, false);
+        assert!(!recorder.borrow().dropped);
 
         let mut iter = map.into_iter();
         assert_eq!(recorder.borrow().clones, 0);
-        assert_eq!(recorder.borrow().dropped, false);
+        assert!(!recorder.borrow().dropped);
 
         let (key, value) = iter.next().unwrap();
         println!("key: {}, value: {:?}", key, value);
     }
     assert_eq!(recorder.borrow().clones, 0);
-    assert_eq!(recorder.borrow().dropped, true);
+    assert!(recorder.borrow().dropped);
 }
 
 #[cfg(feature = "test-no-extra-deps")]
@@ -83,3 +83,80 @@ fn test_non_ord() {
     assert_eq!((&map).into_iter().count(), 0);
     assert_eq!(map.into_iter().count(), 0);
 }
+
+#[cfg(feature = "test-insert")]
+#[test]
+fn test_overwrite_moves_pair_out() {
+    use two_way_map::{Overwritten, TwoWayMap};
+
+    let (recorder, dummy) = recorder::create_dummy();
+
+    let mut map = TwoWayMap::new();
+    map.insert(1, dummy);
+
+    // Dummies compare by their clone count, so a fresh one is equal to the stored one
+    match map.insert(1, recorder::Dummy::default()) {
+        Overwritten::Pair(left, dummy) => {
+            assert_eq!(left, 1);
+            assert!(!recorder.borrow().dropped);
+            drop(dummy);
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+    assert_eq!(recorder.borrow().clones, 0);
+    assert!(recorder.borrow().dropped);
+}
+
+#[cfg(feature = "test-drain")]
+#[test]
+fn test_extract_if_moves_pairs_out() {
+    use two_way_map::TwoWayMap;
+
+    let (recorder, dummy) = recorder::create_dummy();
+
+    let mut map = TwoWayMap::new();
+    map.insert(1, dummy);
+    let extracted = map.extract_if(|_, _| true);
+    assert_eq!(recorder.borrow().clones, 0);
+    assert!(!recorder.borrow().dropped);
+    assert!(map.is_empty());
+
+    drop(extracted);
+    assert!(recorder.borrow().dropped);
+}
+
+#[cfg(feature = "test-into-iterator")]
+#[test]
+fn test_into_iter_by_right_moves_pairs_out() {
+    use two_way_map::TwoWayMap;
+
+    let (recorder, dummy) = recorder::create_dummy();
+
+    let mut map = TwoWayMap::new();
+    map.insert(1, dummy);
+    let pairs: Vec<_> = map.into_iter_by_right().collect();
+    assert_eq!(recorder.borrow().clones, 0);
+    assert!(!recorder.borrow().dropped);
+
+    drop(pairs);
+    assert!(recorder.borrow().dropped);
+}
+
+#[cfg(feature = "test-retain")]
+#[test]
+fn test_retain_drops_removed_pairs() {
+    use two_way_map::TwoWayMap;
+
+    let (recorder, dummy) = recorder::create_dummy();
+
+    let mut map = TwoWayMap::new();
+    map.insert(1, dummy);
+    map.retain(|_, _| true);
+    assert_eq!(recorder.borrow().clones, 0);
+    assert!(!recorder.borrow().dropped);
+
+    map.retain(|_, _| false);
+    assert_eq!(recorder.borrow().clones, 0);
+    assert!(recorder.borrow().dropped);
+    assert!(map.is_empty());
+}
@@ -0,0 +1,334 @@
+mod bomb;
+
+// Runs `op` on copies of `map`, letting the n-th comparison, hash or clone panic for n = 0,
+// 1, ... until `op` gets through, and returns the number of panics. `snapshot` reads a map
+// through both of its indexes and checks that they agree. Every copy `op` panicked on must
+// hold the pairs of `map`, or those it holds once `op` is done: in debug builds a map checks
+// itself after some operations, which compares too.
+#[cfg(any(
+    feature = "test-panic-safety",
+    feature = "test-bi-multi-map",
+    feature = "test-indexed-multi-map"
+))]
+fn check_unwinding<M, S>(map: &M, snapshot: impl Fn(&M) -> S, mut op: impl FnMut(&mut M)) -> usize
+where
+    M: Clone,
+    S: PartialEq + std::fmt::Debug,
+{
+    use std::panic::{AssertUnwindSafe, catch_unwind};
+
+    let expected = snapshot(map);
+    let mut done = map.clone();
+    op(&mut done);
+    let done = snapshot(&done);
+
+    for n in 0..10_000 {
+        let mut copy = map.clone();
+        bomb::arm(n);
+        let result = catch_unwind(AssertUnwindSafe(|| op(&mut copy)));
+        bomb::disarm();
+        let after = snapshot(&copy);
+        if result.is_ok() {
+            assert_eq!(after, done);
+            return n;
+        }
+        assert!(
+            after == expected || after == done,
+            "after panicking at {n}: {after:?}"
+        );
+    }
+    panic!("the operation never got through");
+}
+
+#[cfg(feature = "test-panic-safety")]
+mod test_panic_safety {
+    use std::panic::{AssertUnwindSafe, catch_unwind};
+
+    use super::bomb::{self, Bomb};
+    use two_way_map::{HashTwoWayMap, Hashed, Ordered, SideKey, TwoWayMap};
+
+    type Snapshot = (Vec<(i32, i32)>, Vec<(i32, i32)>);
+
+    // The pairs found through each index, sorted so that hashed sides compare equal
+    fn snapshot<LS: SideKey<Bomb>, RS: SideKey<Bomb>>(
+        map: &TwoWayMap<Bomb, Bomb, LS, RS>,
+    ) -> Snapshot {
+        assert_eq!(map.check_invariants(), Ok(()));
+        let mut by_left: Vec<_> = map.pairs().map(|(l, r)| (l.0, r.0)).collect();
+        let mut by_right: Vec<_> = map.pairs_by_right().map(|(l, r)| (l.0, r.0)).collect();
+        by_left.sort_unstable();
+        by_right.sort_unstable();
+        (by_left, by_right)
+    }
+
+    fn sample<LS: SideKey<Bomb>, RS: SideKey<Bomb>>() -> TwoWayMap<Bomb, Bomb, LS, RS>
+    where
+        TwoWayMap<Bomb, Bomb, LS, RS>: Default,
+    {
+        (0..20).map(|i| (Bomb(i), Bomb(100 + i))).collect()
+    }
+
+    fn check_unwinding<LS, RS>(
+        map: &TwoWayMap<Bomb, Bomb, LS, RS>,
+        op: impl FnMut(&mut TwoWayMap<Bomb, Bomb, LS, RS>),
+    ) -> usize
+    where
+        LS: SideKey<Bomb>,
+        RS: SideKey<Bomb>,
+    {
+        super::check_unwinding(map, snapshot, op)
+    }
+
+    #[test]
+    fn test_insert() {
+        let map: TwoWayMap<Bomb, Bomb> = sample();
+
+        // New pair, same left value, same right value, same pair and two collisions
+        for (left, right) in [(50, 150), (3, 150), (50, 103), (3, 103), (3, 107)] {
+            let panics = check_unwinding(&map, |map| {
+                map.insert(Bomb(left), Bomb(right));
+            });
+            assert!(panics > 0);
+        }
+    }
+
+    #[test]
+    fn test_insert_no_overwrite() {
+        let map: TwoWayMap<Bomb, Bomb> = sample();
+        check_unwinding(&map, |map| {
+            map.insert_no_overwrite(Bomb(50), Bomb(150)).unwrap();
+        });
+        check_unwinding(&map, |map| {
+            map.insert_no_overwrite(Bomb(3), Bomb(150)).unwrap_err();
+        });
+    }
+
+    #[test]
+    fn test_remove() {
+        let map: TwoWayMap<Bomb, Bomb> = sample();
+        let panics = check_unwinding(&map, |map| {
+            assert_eq!(map.remove_by_left(&Bomb(7)).unwrap().1.0, 107);
+        });
+        assert!(panics > 0);
+
+        let panics = check_unwinding(&map, |map| {
+            assert_eq!(map.remove_by_right(&Bomb(107)).unwrap().1.0, 7);
+        });
+        assert!(panics > 0);
+    }
+
+    #[test]
+    fn test_replace() {
+        let map: TwoWayMap<Bomb, Bomb> = sample();
+        let panics = check_unwinding(&map, |map| {
+            assert_eq!(map.replace_left(&Bomb(7), Bomb(50)).unwrap().0, 7);
+        });
+        assert!(panics > 0);
+
+        let panics = check_unwinding(&map, |map| {
+            assert_eq!(map.replace_right(&Bomb(107), Bomb(150)).unwrap().0, 107);
+        });
+        assert!(panics > 0);
+    }
+
+    #[test]
+    fn test_retain() {
+        let map: TwoWayMap<Bomb, Bomb> = sample();
+
+        // The predicate compares, so it panics part way through the pairs
+        let panics = check_unwinding(&map, |map| {
+            map.retain(|left, _| *left < Bomb(10));
+            assert_eq!(map.len(), 10);
+        });
+        assert!(panics >= 20);
+
+        let panics = check_unwinding(&map, |map| {
+            map.retain_by_right(|_, right| *right >= Bomb(110));
+            assert_eq!(map.len(), 10);
+        });
+        assert!(panics >= 20);
+    }
+
+    #[test]
+    fn test_pop() {
+        let map: TwoWayMap<Bomb, Bomb> = sample();
+        check_unwinding(&map, |map| {
+            assert_eq!(map.pop_first_by_left().unwrap().0.0, 0);
+        });
+        check_unwinding(&map, |map| {
+            assert_eq!(map.pop_last_by_right().unwrap().0.0, 119);
+        });
+    }
+
+    #[test]
+    fn test_clone() {
+        let map: TwoWayMap<Bomb, Bomb> = sample();
+        let expected = snapshot(&map);
+        for n in 0.. {
+            bomb::arm(n);
+            let result = catch_unwind(AssertUnwindSafe(|| map.clone()));
+            bomb::disarm();
+
+            assert_eq!(map.check_invariants(), Ok(()));
+            assert_eq!(snapshot(&map), expected);
+            if let Ok(copy) = result {
+                assert_eq!(snapshot(&copy), expected);
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_hashed_sides() {
+        let map: HashTwoWayMap<Bomb, Bomb> = sample();
+        for (left, right) in [(50, 150), (3, 107)] {
+            check_unwinding(&map, |map| {
+                map.insert(Bomb(left), Bomb(right));
+            });
+        }
+        check_unwinding(&map, |map| {
+            map.remove_by_left(&Bomb(7)).unwrap();
+        });
+
+        let map: TwoWayMap<Bomb, Bomb, Ordered, Hashed> = sample();
+        check_unwinding(&map, |map| {
+            map.insert(Bomb(3), Bomb(107));
+        });
+        check_unwinding(&map, |map| {
+            map.retain(|left, _| *left < Bomb(10));
+        });
+    }
+
+    #[test]
+    fn test_entry_and_cursor() {
+        let map: TwoWayMap<Bomb, Bomb> = sample();
+        check_unwinding(&map, |map| {
+            map.left_entry(Bomb(50)).or_insert(Bomb(150)).unwrap();
+        });
+        check_unwinding(&map, |map| {
+            let mut cursor = map.left_cursor_mut_at(std::ops::Bound::Included(&Bomb(5)));
+            assert_eq!(cursor.remove_current().unwrap().0.0, 5);
+        });
+    }
+}
+
+#[cfg(feature = "test-bi-multi-map")]
+mod test_bi_multi_map {
+    use super::bomb::Bomb;
+    use two_way_map::BiMultiMap;
+
+    // The pairs found through the left index, after checking that the right index and the
+    // length agree with them
+    fn snapshot(map: &BiMultiMap<Bomb, Bomb>) -> Vec<(i32, i32)> {
+        let by_left: Vec<_> = map.pairs().map(|(l, r)| (l.0, r.0)).collect();
+        let mut by_right: Vec<_> = map.pairs_by_right().map(|(l, r)| (l.0, r.0)).collect();
+        by_right.sort_unstable();
+        assert_eq!(by_left, by_right);
+        assert_eq!(map.len(), by_left.len());
+        by_left
+    }
+
+    // Every left value from 0 to 3 paired with every right value from 0 to 2
+    fn sample() -> BiMultiMap<Bomb, Bomb> {
+        (0..12).map(|i| (Bomb(i % 4), Bomb(i % 3))).collect()
+    }
+
+    fn check_unwinding(
+        map: &BiMultiMap<Bomb, Bomb>,
+        op: impl FnMut(&mut BiMultiMap<Bomb, Bomb>),
+    ) -> usize {
+        super::check_unwinding(map, snapshot, op)
+    }
+
+    #[test]
+    fn test_insert() {
+        let map = sample();
+        for (left, right) in [(9, 9), (1, 9), (9, 1), (1, 1)] {
+            let panics = check_unwinding(&map, |map| {
+                map.insert(Bomb(left), Bomb(right));
+            });
+            assert!(panics > 0);
+        }
+    }
+
+    #[test]
+    fn test_remove() {
+        let map = sample();
+
+        // (0, 0) is the pair both of its groups are keyed by, (1, 2) neither
+        for (left, right) in [(0, 0), (1, 2), (3, 2)] {
+            let panics = check_unwinding(&map, |map| {
+                assert!(map.remove_pair(&Bomb(left), &Bomb(right)).is_some());
+            });
+            assert!(panics > 0);
+        }
+
+        let panics = check_unwinding(&map, |map| {
+            assert_eq!(map.remove_all_by_left(&Bomb(1)).len(), 3);
+        });
+        assert!(panics > 0);
+        let panics = check_unwinding(&map, |map| {
+            assert_eq!(map.remove_all_by_right(&Bomb(0)).len(), 4);
+        });
+        assert!(panics > 0);
+    }
+}
+
+#[cfg(feature = "test-indexed-multi-map")]
+mod test_indexed_multi_map {
+    use super::bomb::Bomb;
+    use two_way_map::IndexedMultiMap;
+
+    fn snapshot(map: &IndexedMultiMap<Bomb, Bomb>) -> Vec<(i32, i32)> {
+        let by_left: Vec<_> = map.pairs().map(|(l, r)| (l.0, r.0)).collect();
+        let mut by_right: Vec<_> = map.pairs_by_right().map(|(l, r)| (l.0, r.0)).collect();
+        by_right.sort_unstable();
+        assert_eq!(by_left, by_right);
+        by_left
+    }
+
+    // Right values from 0 to 11, owned by their remainder modulo 4
+    fn sample() -> IndexedMultiMap<Bomb, Bomb> {
+        (0..12).map(|i| (Bomb(i % 4), Bomb(i))).collect()
+    }
+
+    fn check_unwinding(
+        map: &IndexedMultiMap<Bomb, Bomb>,
+        op: impl FnMut(&mut IndexedMultiMap<Bomb, Bomb>),
+    ) -> usize {
+        super::check_unwinding(map, snapshot, op)
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        let map = sample();
+        check_unwinding(&map, |map| map.insert(Bomb(1), Bomb(20)).unwrap());
+        check_unwinding(&map, |map| map.insert(Bomb(9), Bomb(20)).unwrap());
+
+        // 0 is the right value the group of its owner is keyed by
+        for right in [0, 5] {
+            let panics = check_unwinding(&map, |map| {
+                assert!(map.remove_by_right(&Bomb(right)).is_some());
+            });
+            assert!(panics > 0);
+        }
+
+        let panics = check_unwinding(&map, |map| {
+            assert_eq!(map.remove_left_cascade(&Bomb(2)).len(), 3);
+        });
+        assert!(panics > 0);
+    }
+
+    #[test]
+    fn test_reparent() {
+        let map = sample();
+
+        // To another owner, to a new one, and to the owner it already has
+        for left in [1, 7, 2] {
+            let panics = check_unwinding(&map, |map| {
+                assert_eq!(map.reparent(&Bomb(6), Bomb(left)), Ok(Bomb(2)));
+            });
+            assert!(panics > 0);
+        }
+    }
+}
@@ -17,9 +17,7 @@ impl PartialEq for Dummy {
     }
 }
 
-impl Eq for Dummy {
-    fn assert_receiver_is_total_eq(&self) {}
-}
+impl Eq for Dummy {}
 
 impl PartialOrd for Dummy {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
@@ -65,13 +63,13 @@ pub fn create_dummy() -> (Rc<RefCell<Recorder>>, Dummy) {
 fn test_dummy() {
     let (recorder, dummy) = create_dummy();
     assert_eq!(recorder.borrow().clones, 0);
-    assert_eq!(recorder.borrow().dropped, false);
+    assert!(!recorder.borrow().dropped);
 
     let dummy_clone = dummy.clone();
     assert_eq!(recorder.borrow().clones, 1);
-    assert_eq!(recorder.borrow().dropped, false);
+    assert!(!recorder.borrow().dropped);
 
     drop(dummy_clone);
     assert_eq!(recorder.borrow().clones, 1);
-    assert_eq!(recorder.borrow().dropped, true);
+    assert!(recorder.borrow().dropped);
 }